@@ -0,0 +1,814 @@
+use std::{fmt, num::ParseIntError, str::FromStr};
+
+use bevy::{
+    math::{const_ivec2, Mat2},
+    prelude::*,
+};
+
+pub const SQRT_3: f32 = 1.732_050_8;
+
+// Axial offsets to the six neighbours, in the same order as `neighbour_iter`.
+const DIRECTIONS: [IVec2; 6] = [
+    const_ivec2!([1, 0]),
+    const_ivec2!([1, -1]),
+    const_ivec2!([0, -1]),
+    const_ivec2!([-1, 0]),
+    const_ivec2!([-1, 1]),
+    const_ivec2!([0, 1]),
+];
+
+/// Which hexes count towards a cell's neighbours.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Neighbourhood {
+    /// The six adjacent hexes.
+    #[default]
+    Ring1,
+    /// The six adjacent hexes and the twelve two steps away.
+    Ring1And2,
+}
+
+/// Size of a single hex and the distance between the centers of neighbouring
+/// columns and rows, as laid out by `axial_to_screen`.
+pub struct HexDimensions {
+    pub width: f32,
+    pub height: f32,
+    pub horizontal_spacing: f32,
+    pub vertical_spacing: f32,
+}
+
+pub fn hex_dimensions(orientation: HexOrientation, size: f32) -> HexDimensions {
+    match orientation {
+        HexOrientation::FlatTop => HexDimensions {
+            width: size * 2.0,
+            height: SQRT_3 * size,
+            horizontal_spacing: size * 3. / 2.,
+            vertical_spacing: SQRT_3 * size,
+        },
+        HexOrientation::PointyTop => HexDimensions {
+            width: SQRT_3 * size,
+            height: size * 2.0,
+            horizontal_spacing: SQRT_3 * size,
+            vertical_spacing: size * 3. / 2.,
+        },
+    }
+}
+
+#[derive(Copy, Clone, Component, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(from = "[i32; 2]", into = "[i32; 2]")
+)]
+pub struct AxialCoordinate(pub IVec2);
+impl AxialCoordinate {
+    pub fn q(&self) -> i32 {
+        self.0.x
+    }
+    pub fn r(&self) -> i32 {
+        self.0.y
+    }
+    pub fn s(&self) -> i32 {
+        -self.q() - self.r()
+    }
+
+    pub fn new(q: i32, r: i32) -> Self {
+        Self(IVec2::new(q, r))
+    }
+
+    /// Number of hex steps between `self` and `other`, computed in cube space.
+    pub fn distance(&self, other: &AxialCoordinate) -> i32 {
+        CubeCoordinate::from(*self).distance(&CubeCoordinate::from(*other))
+    }
+
+    /// The six hexes adjacent to `self`. Called for every living cell on every tick, so
+    /// it walks the `DIRECTIONS` constant rather than allocating the offsets.
+    pub fn neighbour_iter(&self) -> impl Iterator<Item = AxialCoordinate> + '_ {
+        DIRECTIONS.iter().map(|n| AxialCoordinate(self.0 + *n))
+    }
+
+    /// The hexes around `self` that make up `neighbourhood`, starting with the six in
+    /// `neighbour_iter` order.
+    pub fn neighbours(
+        &self,
+        neighbourhood: Neighbourhood,
+    ) -> impl Iterator<Item = AxialCoordinate> + '_ {
+        let outer = match neighbourhood {
+            Neighbourhood::Ring1 => 0,
+            Neighbourhood::Ring1And2 => 12,
+        };
+        self.neighbour_iter().chain(self.ring(2).take(outer))
+    }
+
+    /// The six neighbours of `self` on a `width` x `height` torus, each mapped back
+    /// onto the board with `wrap`.
+    pub fn neighbour_iter_wrapped(
+        &self,
+        width: i32,
+        height: i32,
+    ) -> impl Iterator<Item = AxialCoordinate> + '_ {
+        self.neighbour_iter()
+            .map(move |neighbour| neighbour.wrap(width, height))
+    }
+
+    /// All hexes at exactly `radius` steps from `self`. A radius of 0 yields just `self`.
+    pub fn ring(&self, radius: u32) -> impl Iterator<Item = AxialCoordinate> {
+        let center = (radius == 0).then_some(*self);
+        // Walk out to one corner, then follow each of the six edges `radius` steps.
+        let start = AxialCoordinate(self.0 + DIRECTIONS[4] * radius as i32);
+        let steps =
+            (0..6).flat_map(move |side| std::iter::repeat_n(DIRECTIONS[side], radius as usize));
+        center.into_iter().chain(steps.scan(start, |hex, dir| {
+            let current = *hex;
+            hex.0 += dir;
+            Some(current)
+        }))
+    }
+
+    /// The hexes on a straight line from `self` to `other`, both ends included. Each
+    /// hex in the result is adjacent to the one before it.
+    pub fn line_to(&self, other: &AxialCoordinate) -> Vec<AxialCoordinate> {
+        let steps = self.distance(other);
+        // Nudge both ends off the grid so points exactly between two hexes round consistently.
+        let nudge = Vec3::new(1e-3, 2e-3, -3e-3);
+        let start = CubeCoordinate::from(*self).0.as_vec3() + nudge;
+        let end = CubeCoordinate::from(*other).0.as_vec3() + nudge;
+        (0..=steps)
+            .map(|i| {
+                let t = if steps == 0 {
+                    0.
+                } else {
+                    i as f32 / steps as f32
+                };
+                cube_round(start.lerp(end, t)).into()
+            })
+            .collect()
+    }
+
+    /// Every hex within `n` steps of `self`, `1 + 3 * n * (n + 1)` in total.
+    pub fn within_range(&self, n: i32) -> impl Iterator<Item = AxialCoordinate> {
+        let center = *self;
+        (-n..=n).flat_map(move |dq| {
+            ((-n).max(-dq - n)..=n.min(-dq + n))
+                .map(move |dr| AxialCoordinate(center.0 + IVec2::new(dq, dr)))
+        })
+    }
+
+    /// Maps `self` onto a `width` x `height` torus centered on the origin.
+    ///
+    /// The wrap is done in odd-q offset space (columns of flat-top hexes) so that
+    /// hexes adjacent across the seam stay adjacent. `width` must be even, otherwise
+    /// the column parity flips at the seam and adjacency breaks.
+    pub fn wrap(&self, width: i32, height: i32) -> AxialCoordinate {
+        debug_assert!(width % 2 == 0, "wrap width must be even, got {}", width);
+        let (col, row) = axial_to_offset(self);
+        let col = (col + width / 2).rem_euclid(width) - width / 2;
+        let row = (row + height / 2).rem_euclid(height) - height / 2;
+        offset_to_axial(col, row)
+    }
+
+    /// `self` followed by each ring out to `radius`, covering a filled hexagon of
+    /// `1 + 3 * radius * (radius + 1)` hexes.
+    pub fn spiral(&self, radius: u32) -> impl Iterator<Item = AxialCoordinate> {
+        let center = *self;
+        (0..=radius).flat_map(move |r| center.ring(r))
+    }
+
+    /// `self` in doubled-height coordinates, returning `(col, row)` with `col = q` and
+    /// `row = 2 * r + q`. Columns run up the screen for flat-top hexes in steps of
+    /// half a hex, so vertically stacked hexes differ by 2 in `row` and `col + row` is
+    /// always even. Doubled-width coordinates are the pointy-top counterpart.
+    pub fn to_doubled(&self) -> (i32, i32) {
+        (self.q(), 2 * self.r() + self.q())
+    }
+
+    /// The inverse of `to_doubled`. `col + row` must be even; odd sums fall between
+    /// hexes and are rounded down a row.
+    pub fn from_doubled(col: i32, row: i32) -> AxialCoordinate {
+        AxialCoordinate::new(col, (row - col).div_euclid(2))
+    }
+}
+
+/// Converts odd-q offset coordinates, where every odd column is shifted half a hex
+/// along the row axis, to axial. Rectangles in offset space are rectangles on screen
+/// for flat-top hexes.
+pub fn offset_to_axial(col: i32, row: i32) -> AxialCoordinate {
+    AxialCoordinate::new(col, row - (col - (col & 1)) / 2)
+}
+
+/// The inverse of `offset_to_axial`, returning `(col, row)`.
+pub fn axial_to_offset(coord: &AxialCoordinate) -> (i32, i32) {
+    (coord.q(), coord.r() + (coord.q() - (coord.q() & 1)) / 2)
+}
+
+/// Why a coordinate string failed to parse.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseCoordinateError {
+    ComponentCount { expected: usize, found: usize },
+    InvalidComponent(ParseIntError),
+    NotOnPlane { q: i32, r: i32, s: i32 },
+}
+
+impl fmt::Display for ParseCoordinateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseCoordinateError::ComponentCount { expected, found } => write!(
+                f,
+                "expected {} comma separated components, found {}",
+                expected, found
+            ),
+            ParseCoordinateError::InvalidComponent(e) => write!(f, "invalid component: {}", e),
+            ParseCoordinateError::NotOnPlane { q, r, s } => {
+                write!(f, "{} + {} + {} is not 0", q, r, s)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseCoordinateError {}
+
+/// Parses exactly `N` comma separated integers, allowing whitespace around each.
+fn parse_components<const N: usize>(input: &str) -> Result<[i32; N], ParseCoordinateError> {
+    let parts: Vec<&str> = input.split(',').collect();
+    if parts.len() != N {
+        return Err(ParseCoordinateError::ComponentCount {
+            expected: N,
+            found: parts.len(),
+        });
+    }
+    let mut components = [0; N];
+    for (component, part) in components.iter_mut().zip(parts) {
+        *component = part
+            .trim()
+            .parse()
+            .map_err(ParseCoordinateError::InvalidComponent)?;
+    }
+    Ok(components)
+}
+
+/// Formats as `q,r`.
+impl fmt::Display for AxialCoordinate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{}", self.q(), self.r())
+    }
+}
+
+impl FromStr for AxialCoordinate {
+    type Err = ParseCoordinateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let [q, r] = parse_components(s)?;
+        Ok(AxialCoordinate::new(q, r))
+    }
+}
+
+/// Formats as `q,r,s`.
+impl fmt::Display for CubeCoordinate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{},{}", self.q(), self.r(), self.s())
+    }
+}
+
+impl FromStr for CubeCoordinate {
+    type Err = ParseCoordinateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CubeCoordinate::try_from(parse_components::<3>(s)?)
+    }
+}
+
+impl From<[i32; 2]> for AxialCoordinate {
+    fn from([q, r]: [i32; 2]) -> Self {
+        AxialCoordinate::new(q, r)
+    }
+}
+
+impl From<AxialCoordinate> for [i32; 2] {
+    fn from(ac: AxialCoordinate) -> Self {
+        [ac.q(), ac.r()]
+    }
+}
+
+impl TryFrom<[i32; 3]> for CubeCoordinate {
+    type Error = ParseCoordinateError;
+
+    fn try_from([q, r, s]: [i32; 3]) -> Result<Self, Self::Error> {
+        CubeCoordinate::try_new(q, r, s).ok_or(ParseCoordinateError::NotOnPlane { q, r, s })
+    }
+}
+
+impl From<CubeCoordinate> for [i32; 3] {
+    fn from(cc: CubeCoordinate) -> Self {
+        [cc.q(), cc.r(), cc.s()]
+    }
+}
+
+impl From<CubeCoordinate> for AxialCoordinate {
+    fn from(cc: CubeCoordinate) -> Self {
+        AxialCoordinate::new(cc.q(), cc.r())
+    }
+}
+
+impl From<AxialCoordinate> for CubeCoordinate {
+    fn from(ac: AxialCoordinate) -> Self {
+        CubeCoordinate::new(ac.q(), ac.r(), ac.s())
+    }
+}
+
+#[derive(Copy, Clone, Component, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "[i32; 3]", into = "[i32; 3]")
+)]
+pub struct CubeCoordinate(pub IVec3);
+impl CubeCoordinate {
+    pub fn q(&self) -> i32 {
+        self.0.x
+    }
+    pub fn r(&self) -> i32 {
+        self.0.y
+    }
+    pub fn s(&self) -> i32 {
+        self.0.z
+    }
+
+    pub fn new(q: i32, r: i32, s: i32) -> Self {
+        debug_assert_eq!(q + r + s, 0, "cube coordinates must satisfy q + r + s == 0");
+        Self(IVec3::new(q, r, s))
+    }
+
+    /// Like `new`, but returns `None` instead of asserting when `q + r + s != 0`.
+    pub fn try_new(q: i32, r: i32, s: i32) -> Option<Self> {
+        (q + r + s == 0).then(|| Self(IVec3::new(q, r, s)))
+    }
+
+    /// Number of hex steps between `self` and `other`.
+    pub fn distance(&self, other: &CubeCoordinate) -> i32 {
+        (self.0 - other.0).abs().max_element()
+    }
+
+    /// Rotates 60 degrees about the origin, `(q, r, s) -> (-s, -q, -r)`.
+    pub fn rotate_left(&self) -> CubeCoordinate {
+        CubeCoordinate::new(-self.s(), -self.q(), -self.r())
+    }
+
+    /// Rotates 60 degrees about the origin the other way, `(q, r, s) -> (-r, -s, -q)`.
+    pub fn rotate_right(&self) -> CubeCoordinate {
+        CubeCoordinate::new(-self.r(), -self.s(), -self.q())
+    }
+
+    /// Mirrors across the q axis through the origin, `(q, r, s) -> (q, s, r)`.
+    pub fn reflect_q(&self) -> CubeCoordinate {
+        CubeCoordinate::new(self.q(), self.s(), self.r())
+    }
+
+    /// Mirrors across the r axis through the origin, `(q, r, s) -> (s, r, q)`.
+    pub fn reflect_r(&self) -> CubeCoordinate {
+        CubeCoordinate::new(self.s(), self.r(), self.q())
+    }
+
+    /// Mirrors across the s axis through the origin, `(q, r, s) -> (r, q, s)`.
+    pub fn reflect_s(&self) -> CubeCoordinate {
+        CubeCoordinate::new(self.r(), self.q(), self.s())
+    }
+}
+
+/// Whether hexes have a flat edge or a corner at the top.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum HexOrientation {
+    #[default]
+    FlatTop,
+    PointyTop,
+}
+
+impl HexOrientation {
+    // Column-major matrices taking axial coordinates to screen space and back.
+    fn basis(&self) -> Mat2 {
+        const FLAT_BASIS_C: [f32; 4] = [3. / 2., SQRT_3 / 2., 0., SQRT_3];
+        const POINTY_BASIS_C: [f32; 4] = [SQRT_3, 0., SQRT_3 / 2., 3. / 2.];
+        match self {
+            HexOrientation::FlatTop => Mat2::from_cols_array(&FLAT_BASIS_C),
+            HexOrientation::PointyTop => Mat2::from_cols_array(&POINTY_BASIS_C),
+        }
+    }
+
+    fn inverse_basis(&self) -> Mat2 {
+        const FLAT_BASIS_C: [f32; 4] = [2. / 3., -1. / 3., 0., SQRT_3 / 3.];
+        const POINTY_BASIS_C: [f32; 4] = [SQRT_3 / 3., 0., -1. / 3., 2. / 3.];
+        match self {
+            HexOrientation::FlatTop => Mat2::from_cols_array(&FLAT_BASIS_C),
+            HexOrientation::PointyTop => Mat2::from_cols_array(&POINTY_BASIS_C),
+        }
+    }
+}
+
+pub fn axial_to_screen(orientation: HexOrientation, size: f32, hex_pos: &AxialCoordinate) -> Vec2 {
+    size * orientation.basis() * hex_pos.0.as_vec2()
+}
+
+/// The inverse of `axial_to_screen`: fractional cube coordinates of a screen point,
+/// to be snapped to a hex with `cube_round`.
+pub fn screen_to_cube_float(orientation: HexOrientation, size: f32, point: Vec2) -> Vec3 {
+    let axial_float = orientation.inverse_basis() * point / size;
+    Vec3::from((axial_float, -axial_float.x - axial_float.y))
+}
+
+/// The hex containing the screen point `point`: `screen_to_cube_float` snapped to a
+/// hex with `cube_round`.
+pub fn screen_to_axial(orientation: HexOrientation, size: f32, point: Vec2) -> AxialCoordinate {
+    cube_round(screen_to_cube_float(orientation, size, point)).into()
+}
+
+/// Whether `local`, an offset from a hex center, falls inside a hexagon of the given
+/// orientation and inner radius `apothem`.
+pub fn in_hexagon(orientation: HexOrientation, local: Vec2, apothem: f32) -> bool {
+    // Pointy-top hexagons are flat-top ones with the axes swapped.
+    let p = match orientation {
+        HexOrientation::FlatTop => local,
+        HexOrientation::PointyTop => Vec2::new(local.y, local.x),
+    };
+    // Distance to each pair of opposite edges of a flat-top hexagon.
+    let normals = [
+        Vec2::new(0., 1.),
+        Vec2::new(SQRT_3 / 2., 0.5),
+        Vec2::new(-SQRT_3 / 2., 0.5),
+    ];
+    normals.iter().all(|n| p.dot(*n).abs() <= apothem)
+}
+
+/// The hex containing fractional cube coordinates `cube`. The component that moved
+/// furthest while rounding is rebuilt from the other two, so the result always lies on
+/// the `q + r + s = 0` plane.
+pub fn cube_round(cube: Vec3) -> CubeCoordinate {
+    let rounded_cube = cube.round();
+    let diff = (rounded_cube - cube).abs();
+
+    let mut ret_cube: IVec3 = rounded_cube.as_ivec3();
+    if diff.x > diff.y && diff.x > diff.z {
+        ret_cube.x = -ret_cube.y - ret_cube.z;
+    } else if diff.y > diff.z {
+        ret_cube.y = -ret_cube.x - ret_cube.z;
+    } else {
+        ret_cube.z = -ret_cube.x - ret_cube.y;
+    }
+
+    CubeCoordinate(ret_cube)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::utils::HashSet;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    const ORIENTATIONS: [HexOrientation; 2] = [HexOrientation::FlatTop, HexOrientation::PointyTop];
+
+    /// Every coordinate with `|q|` and `|r|` at most `n`.
+    fn square(n: i32) -> impl Iterator<Item = AxialCoordinate> {
+        (-n..=n).flat_map(move |q| (-n..=n).map(move |r| AxialCoordinate::new(q, r)))
+    }
+
+    #[test]
+    fn screen_round_trip() {
+        for orientation in ORIENTATIONS {
+            for coord in square(50) {
+                let point = axial_to_screen(orientation, 10., &coord);
+                assert_eq!(screen_to_axial(orientation, 10., point), coord);
+            }
+        }
+    }
+
+    #[test]
+    fn cube_round_stays_on_plane() {
+        for orientation in ORIENTATIONS {
+            for x in -50..50 {
+                for y in -50..50 {
+                    // Points between hex centers, so every rounding branch is taken.
+                    let point = Vec2::new(x as f32 * 2.7 + 0.3, y as f32 * 3.1 - 0.7);
+                    let cube = cube_round(screen_to_cube_float(orientation, 10., point));
+                    assert_eq!(cube.q() + cube.r() + cube.s(), 0, "{:?}", cube);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn random_coordinates_round_trip() {
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..10_000 {
+            let coord =
+                AxialCoordinate::new(rng.gen_range(-1000..=1000), rng.gen_range(-1000..=1000));
+            let size = rng.gen_range(1f32..100.);
+            for orientation in ORIENTATIONS {
+                let point = axial_to_screen(orientation, size, &coord);
+                assert_eq!(
+                    screen_to_axial(orientation, size, point),
+                    coord,
+                    "size {}",
+                    size
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn cube_round_of_random_points_stays_on_plane() {
+        let mut rng = StdRng::seed_from_u64(1);
+        for _ in 0..10_000 {
+            let point = Vec2::new(rng.gen_range(-1e4..1e4), rng.gen_range(-1e4..1e4));
+            let size = rng.gen_range(1f32..100.);
+            for orientation in ORIENTATIONS {
+                let cube = cube_round(screen_to_cube_float(orientation, size, point));
+                assert_eq!(cube.q() + cube.r() + cube.s(), 0, "{:?}", cube);
+            }
+        }
+    }
+
+    #[test]
+    fn distance_along_lines_and_diagonals() {
+        let origin = AxialCoordinate::new(0, 0);
+        assert_eq!(origin.distance(&origin), 0);
+        assert_eq!(origin.distance(&AxialCoordinate::new(5, 0)), 5);
+        assert_eq!(origin.distance(&AxialCoordinate::new(0, -5)), 5);
+        assert_eq!(origin.distance(&AxialCoordinate::new(5, -5)), 5);
+        // Off the three axes the steps add up.
+        assert_eq!(origin.distance(&AxialCoordinate::new(2, 1)), 3);
+        assert_eq!(origin.distance(&AxialCoordinate::new(-3, -2)), 5);
+        let other = AxialCoordinate::new(-4, 7);
+        assert_eq!(other.distance(&origin), origin.distance(&other));
+    }
+
+    #[test]
+    fn neighbours_are_one_step_away() {
+        for coord in square(3) {
+            for neighbour in coord.neighbour_iter() {
+                assert_eq!(coord.distance(&neighbour), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn ring_has_six_n_hexes_at_distance_n() {
+        let center = AxialCoordinate::new(2, -3);
+        assert_eq!(center.ring(0).collect::<Vec<_>>(), vec![center]);
+        for radius in 1..10 {
+            let ring: HashSet<AxialCoordinate> = center.ring(radius).collect();
+            assert_eq!(ring.len(), 6 * radius as usize);
+            assert!(ring
+                .iter()
+                .all(|hex| hex.distance(&center) == radius as i32));
+        }
+        let ring: HashSet<AxialCoordinate> = center.ring(1).collect();
+        assert_eq!(ring, center.neighbour_iter().collect());
+    }
+
+    #[test]
+    fn spiral_covers_a_filled_hexagon() {
+        let center = AxialCoordinate::new(-1, 4);
+        for radius in 0..10u32 {
+            let spiral: Vec<AxialCoordinate> = center.spiral(radius).collect();
+            assert_eq!(spiral.len() as u32, 1 + 3 * radius * (radius + 1));
+            assert_eq!(spiral[0], center);
+            let unique: HashSet<AxialCoordinate> = spiral.iter().copied().collect();
+            assert_eq!(unique.len(), spiral.len());
+        }
+    }
+
+    #[test]
+    fn points_inside_a_hex_pick_it() {
+        for orientation in ORIENTATIONS {
+            for coord in square(20) {
+                let center = axial_to_screen(orientation, 10., &coord);
+                for angle in 0..12 {
+                    // Just inside the inner radius, in every direction.
+                    let angle = angle as f32 * 0.5;
+                    let offset = Vec2::new(angle.cos(), angle.sin());
+                    let point = center + offset * 10. * SQRT_3 / 2. * 0.99;
+                    assert_eq!(screen_to_axial(orientation, 10., point), coord);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn six_rotations_are_a_full_turn() {
+        for coord in square(5) {
+            let cube = CubeCoordinate::from(coord);
+            let mut left = cube;
+            let mut right = cube;
+            for turn in 1..=6 {
+                left = left.rotate_left();
+                right = right.rotate_right();
+                assert_eq!(
+                    left == cube,
+                    turn == 6 || coord == AxialCoordinate::new(0, 0)
+                );
+                assert_eq!(
+                    right == cube,
+                    turn == 6 || coord == AxialCoordinate::new(0, 0)
+                );
+            }
+            assert_eq!(cube.rotate_left().rotate_right(), cube);
+            assert_eq!(cube.rotate_right().rotate_left(), cube);
+        }
+    }
+
+    #[test]
+    fn axial_cube_round_trip() {
+        for coord in square(50) {
+            let cube = CubeCoordinate::from(coord);
+            assert_eq!(cube.q() + cube.r() + cube.s(), 0);
+            assert_eq!(
+                (cube.q(), cube.r(), cube.s()),
+                (coord.q(), coord.r(), coord.s())
+            );
+            assert_eq!(AxialCoordinate::from(cube), coord);
+        }
+    }
+
+    #[test]
+    fn try_new_rejects_points_off_the_plane() {
+        assert!(CubeCoordinate::try_new(1, 1, 1).is_none());
+        assert!(CubeCoordinate::try_new(0, 0, 1).is_none());
+        assert_eq!(
+            CubeCoordinate::try_new(1, -1, 0),
+            Some(CubeCoordinate::new(1, -1, 0))
+        );
+        assert!(CubeCoordinate::try_new(0, 0, 0).is_some());
+    }
+
+    /// Checks that `line_to` runs from `start` to `end` in `distance + 1` adjacent hexes.
+    fn assert_contiguous_line(start: AxialCoordinate, end: AxialCoordinate) {
+        let line = start.line_to(&end);
+        assert_eq!(line.len() as i32, start.distance(&end) + 1);
+        assert_eq!(line.first(), Some(&start));
+        assert_eq!(line.last(), Some(&end));
+        for pair in line.windows(2) {
+            assert_eq!(pair[0].distance(&pair[1]), 1, "{:?} has a gap", line);
+        }
+    }
+
+    #[test]
+    fn line_to_is_contiguous() {
+        let origin = AxialCoordinate::new(0, 0);
+        assert_contiguous_line(origin, AxialCoordinate::new(6, 0));
+        assert_contiguous_line(origin, AxialCoordinate::new(4, -4));
+        assert_contiguous_line(origin, AxialCoordinate::new(3, 3));
+        assert_contiguous_line(AxialCoordinate::new(-17, 5), AxialCoordinate::new(23, -31));
+        for end in origin.ring(7) {
+            assert_contiguous_line(origin, end);
+        }
+    }
+
+    #[test]
+    fn line_to_itself_is_one_hex() {
+        let coord = AxialCoordinate::new(3, -8);
+        assert_eq!(coord.line_to(&coord), vec![coord]);
+    }
+
+    #[test]
+    fn within_range_covers_a_filled_hexagon() {
+        let center = AxialCoordinate::new(5, -2);
+        for n in 0..10 {
+            let hexes: Vec<AxialCoordinate> = center.within_range(n).collect();
+            assert_eq!(hexes.len() as i32, 1 + 3 * n * (n + 1));
+            assert!(hexes.iter().all(|hex| hex.distance(&center) <= n));
+            let unique: HashSet<AxialCoordinate> = hexes.iter().copied().collect();
+            assert_eq!(unique, center.spiral(n as u32).collect());
+        }
+    }
+
+    #[test]
+    fn flat_top_spacing_matches_the_layout() {
+        let size = 10.;
+        let dims = hex_dimensions(HexOrientation::FlatTop, size);
+        assert!((dims.horizontal_spacing - 15.).abs() < 1e-4);
+        assert!((dims.vertical_spacing - SQRT_3 * 10.).abs() < 1e-4);
+        assert!((dims.width - 20.).abs() < 1e-4);
+        assert!((dims.height - dims.vertical_spacing).abs() < 1e-4);
+        let screen =
+            |q, r| axial_to_screen(HexOrientation::FlatTop, size, &AxialCoordinate::new(q, r));
+        // The next column over, on the same screen row.
+        let across = screen(2, -1) - screen(0, 0);
+        assert!((across.x - 2. * dims.horizontal_spacing).abs() < 1e-4);
+        assert!(across.y.abs() < 1e-4);
+        let up = screen(0, 1) - screen(0, 0);
+        assert!(up.x.abs() < 1e-4);
+        assert!((up.y - dims.vertical_spacing).abs() < 1e-4);
+    }
+
+    #[test]
+    fn offset_round_trip() {
+        for col in -20..=20 {
+            for row in -20..=20 {
+                let coord = offset_to_axial(col, row);
+                assert_eq!(axial_to_offset(&coord), (col, row));
+            }
+        }
+        for coord in square(20) {
+            let (col, row) = axial_to_offset(&coord);
+            assert_eq!(offset_to_axial(col, row), coord);
+        }
+        // Odd columns, negative ones included, sit half a hex further along the row.
+        assert_eq!(offset_to_axial(1, 0), AxialCoordinate::new(1, 0));
+        assert_eq!(offset_to_axial(-1, 0), AxialCoordinate::new(-1, 1));
+        assert_eq!(offset_to_axial(-3, -2), AxialCoordinate::new(-3, 0));
+    }
+
+    #[test]
+    fn reflecting_twice_is_the_identity() {
+        for coord in square(10) {
+            let cube = CubeCoordinate::from(coord);
+            assert_eq!(cube.reflect_q().reflect_q(), cube);
+            assert_eq!(cube.reflect_r().reflect_r(), cube);
+            assert_eq!(cube.reflect_s().reflect_s(), cube);
+            // Each reflection keeps its own axis and the distance to the origin.
+            assert_eq!(cube.reflect_q().q(), cube.q());
+            assert_eq!(cube.reflect_r().r(), cube.r());
+            assert_eq!(cube.reflect_s().s(), cube.s());
+        }
+    }
+
+    #[test]
+    fn parse_round_trip() {
+        for coord in square(12) {
+            assert_eq!(coord.to_string().parse::<AxialCoordinate>(), Ok(coord));
+            let cube = CubeCoordinate::from(coord);
+            assert_eq!(cube.to_string().parse::<CubeCoordinate>(), Ok(cube));
+        }
+        assert_eq!(" -3 , 7 ".parse(), Ok(AxialCoordinate::new(-3, 7)));
+        assert_eq!("2, -5, 3".parse(), Ok(CubeCoordinate::new(2, -5, 3)));
+    }
+
+    #[test]
+    fn parse_rejects_malformed_input() {
+        assert_eq!(
+            "1".parse::<AxialCoordinate>(),
+            Err(ParseCoordinateError::ComponentCount {
+                expected: 2,
+                found: 1
+            })
+        );
+        assert_eq!(
+            "1,2,-3".parse::<AxialCoordinate>(),
+            Err(ParseCoordinateError::ComponentCount {
+                expected: 2,
+                found: 3
+            })
+        );
+        assert!(matches!(
+            "1,x".parse::<AxialCoordinate>(),
+            Err(ParseCoordinateError::InvalidComponent(_))
+        ));
+        assert!(matches!(
+            "".parse::<AxialCoordinate>(),
+            Err(ParseCoordinateError::ComponentCount { .. })
+        ));
+        assert!(matches!(
+            "1,,2".parse::<CubeCoordinate>(),
+            Err(ParseCoordinateError::InvalidComponent(_))
+        ));
+        assert_eq!(
+            "1,1,1".parse::<CubeCoordinate>(),
+            Err(ParseCoordinateError::NotOnPlane { q: 1, r: 1, s: 1 })
+        );
+    }
+
+    #[test]
+    fn doubled_round_trip() {
+        for coord in square(20) {
+            let (col, row) = coord.to_doubled();
+            assert_eq!((col + row).rem_euclid(2), 0);
+            assert_eq!(AxialCoordinate::from_doubled(col, row), coord);
+        }
+        // Stacked hexes are two rows apart, diagonal neighbours one row and one column.
+        assert_eq!(AxialCoordinate::new(0, 1).to_doubled(), (0, 2));
+        assert_eq!(AxialCoordinate::new(1, 0).to_doubled(), (1, 1));
+        assert_eq!(AxialCoordinate::new(-1, 0).to_doubled(), (-1, -1));
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod serde_tests {
+    use super::*;
+
+    #[test]
+    fn coordinates_serialize_as_arrays() {
+        let coords = vec![
+            AxialCoordinate::new(0, 0),
+            AxialCoordinate::new(3, -7),
+            AxialCoordinate::new(-12, 5),
+        ];
+        let text = ron::to_string(&coords).unwrap();
+        assert_eq!(text, "[(0,0),(3,-7),(-12,5)]");
+        assert_eq!(
+            ron::from_str::<Vec<AxialCoordinate>>(&text).unwrap(),
+            coords
+        );
+        let cubes: Vec<CubeCoordinate> = coords.iter().map(|c| CubeCoordinate::from(*c)).collect();
+        let text = ron::to_string(&cubes).unwrap();
+        assert_eq!(ron::from_str::<Vec<CubeCoordinate>>(&text).unwrap(), cubes);
+        // Cube coordinates off the plane are rejected rather than read in.
+        assert!(ron::from_str::<CubeCoordinate>("(1,1,1)").is_err());
+    }
+}
@@ -0,0 +1,220 @@
+use bevy::{
+    diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+    utils::HashSet,
+};
+
+use hex_of_life::{hex::AxialCoordinate, life::*};
+
+use crate::{
+    BoardShape, BoardTopology, CellAges, CellSpecies, Generation, HoveredHex, LivingCells,
+    OscillatorPeriod, SimulationRules,
+};
+
+const HUD_FONT: &str = "fonts/NotoSans-Medium.ttf";
+const HUD_FONT_SIZE: f32 = 18.;
+
+/// On-screen readouts of the simulation state.
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup_hud)
+            .add_system(update_generation_text)
+            .add_system(update_population_text)
+            .add_system(update_oscillator_text)
+            .add_system(update_fps_text)
+            .add_system(update_probe_text);
+    }
+}
+
+#[derive(Component)]
+struct GenerationText;
+#[derive(Component)]
+struct PopulationText;
+#[derive(Component)]
+struct OscillatorText;
+#[derive(Component)]
+struct FpsText;
+#[derive(Component)]
+struct ProbeText;
+
+fn setup_hud(mut commands: Commands, asset_server: Res<AssetServer>, topology: Res<BoardTopology>) {
+    let font = asset_server.load(HUD_FONT);
+    commands.spawn_bundle(UiCameraBundle::default());
+    commands
+        .spawn_bundle(hud_text(font.clone(), 5.))
+        .insert(GenerationText);
+    commands
+        .spawn_bundle(hud_text(font.clone(), 5. + HUD_FONT_SIZE))
+        .insert(PopulationText);
+    commands
+        .spawn_bundle(hud_text(font.clone(), 5. + 2. * HUD_FONT_SIZE))
+        .insert(OscillatorText);
+    // The board shape is fixed at startup, so this line never needs updating.
+    let mut board_text = hud_text(font.clone(), 5. + 3. * HUD_FONT_SIZE);
+    board_text.text.sections[0].value = match topology.shape {
+        BoardShape::Unbounded => String::new(),
+        BoardShape::Hexagon { radius } => format!("Board radius: {}", radius),
+        BoardShape::Rectangle { cols, rows } => format!("Board: {} x {}", cols, rows),
+    };
+    commands.spawn_bundle(board_text);
+    let mut fps_text = hud_text(font.clone(), 5.);
+    fps_text.style.position.left = Val::Auto;
+    fps_text.style.position.right = Val::Px(5.);
+    fps_text.visibility.is_visible = false;
+    commands.spawn_bundle(fps_text).insert(FpsText);
+    commands.spawn_bundle(hud_text(font, 0.)).insert(ProbeText);
+}
+
+/// An empty line of HUD text anchored to the top left of the window.
+fn hud_text(font: Handle<Font>, top: f32) -> TextBundle {
+    TextBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: Rect {
+                top: Val::Px(top),
+                left: Val::Px(5.),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        text: Text::with_section(
+            "",
+            TextStyle {
+                font,
+                font_size: HUD_FONT_SIZE,
+                color: Color::WHITE,
+            },
+            TextAlignment::default(),
+        ),
+        ..Default::default()
+    }
+}
+
+fn update_generation_text(
+    generation: Res<Generation>,
+    mut query: Query<&mut Text, With<GenerationText>>,
+) {
+    if !generation.is_changed() {
+        return;
+    }
+    for mut text in query.iter_mut() {
+        text.sections[0].value = format!("Generation: {}", generation.0);
+    }
+}
+
+fn update_population_text(
+    living: Res<LivingCells>,
+    mut query: Query<&mut Text, With<PopulationText>>,
+) {
+    if !living.is_changed() {
+        return;
+    }
+    for mut text in query.iter_mut() {
+        text.sections[0].value = format!("Population: {}", living.0.len());
+    }
+}
+
+fn update_oscillator_text(
+    oscillator: Res<OscillatorPeriod>,
+    mut query: Query<&mut Text, With<OscillatorText>>,
+) {
+    if !oscillator.is_changed() {
+        return;
+    }
+    for mut text in query.iter_mut() {
+        text.sections[0].value = match oscillator.0 {
+            Some(period) => format!("Oscillator: period {}", period),
+            None => String::new(),
+        };
+    }
+}
+
+/// Shows the smoothed frame rate in the top right, toggled with F3.
+fn update_fps_text(
+    kb: Res<Input<KeyCode>>,
+    diagnostics: Res<Diagnostics>,
+    mut query: Query<(&mut Text, &mut Visibility), With<FpsText>>,
+) {
+    for (mut text, mut visibility) in query.iter_mut() {
+        if kb.just_pressed(KeyCode::F3) {
+            visibility.is_visible = !visibility.is_visible;
+        }
+        if !visibility.is_visible {
+            continue;
+        }
+        if let Some(fps) = diagnostics
+            .get(FrameTimeDiagnosticsPlugin::FPS)
+            .and_then(|fps| fps.average())
+        {
+            text.sections[0].value = format!("FPS: {:.0}", fps);
+        }
+    }
+}
+
+/// Follows the cursor with the hovered hex's coordinate, its number of living
+/// neighbours and what the next tick will do to it.
+fn update_probe_text(
+    wnds: Res<Windows>,
+    hovered: Res<HoveredHex>,
+    living: Res<LivingCells>,
+    ages: Res<CellAges>,
+    species: Res<CellSpecies>,
+    sim: SimulationRules,
+    q_hex: Query<&AxialCoordinate>,
+    mut query: Query<(&mut Text, &mut Style), With<ProbeText>>,
+) {
+    let wnd = wnds.get_primary();
+    let cursor = wnd.and_then(|wnd| Some((wnd.cursor_position()?, wnd.height())));
+    let coord = hovered.0.and_then(|entity| q_hex.get(entity).ok());
+    for (mut text, mut style) in query.iter_mut() {
+        let (coord, (cursor, height)) = match (coord, cursor) {
+            (Some(coord), Some(cursor)) => (coord, cursor),
+            _ => {
+                text.sections[0].value.clear();
+                continue;
+            }
+        };
+        // The cursor is measured from the bottom of the window, UI from the top.
+        style.position.left = Val::Px(cursor.x + 16.);
+        style.position.top = Val::Px(height - cursor.y + 16.);
+
+        let neighbours: Vec<AxialCoordinate> = coord
+            .neighbours(*sim.neighbourhood)
+            .map(|neighbour| sim.topology.resolve(neighbour))
+            .filter(|neighbour| living.0.contains(neighbour))
+            .collect();
+        // The hex's fate only depends on itself and its neighbours, so simulating just
+        // those gives the same answer as a full tick.
+        let local: HashSet<AxialCoordinate> = neighbours
+            .iter()
+            .copied()
+            .chain(living.0.get(coord).copied())
+            .collect();
+        let (mut next, _) =
+            next_generation_species(&local, &species.0, &sim.rules.0, *sim.neighbourhood, |c| {
+                sim.topology.resolve(c)
+            });
+        apply_max_age(&mut next, &local, &ages.0, &species.0, &sim.rules.0);
+        // Locked hexes and those outside the bounds keep their state, as in `game_of_life`.
+        let alive = living.0.contains(coord);
+        let alive_next = if sim.frozen(coord) {
+            alive
+        } else {
+            next.contains(coord)
+        };
+        let fate = match (alive, alive_next) {
+            (true, true) => "survives",
+            (true, false) => "dies",
+            (false, true) => "born",
+            (false, false) => "stays dead",
+        };
+        text.sections[0].value = format!(
+            "{}\nLiving neighbours: {}\nNext tick: {}",
+            coord,
+            neighbours.len(),
+            fate
+        );
+    }
+}
@@ -0,0 +1,4 @@
+pub mod hex;
+pub mod life;
+pub mod pattern;
+pub mod picking;
@@ -0,0 +1,324 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use bevy::utils::{HashMap, HashSet};
+
+use crate::hex::{AxialCoordinate, Neighbourhood};
+
+/// Neighbour counts for which a living cell survives and a dead cell is born. With
+/// `max_age` set, a cell that has reached that age dies on the next tick whatever its
+/// neighbours, see `apply_max_age`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LifeRules {
+    pub survive: Vec<u8>,
+    pub born: Vec<u8>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub max_age: Option<u32>,
+}
+
+impl Default for LifeRules {
+    fn default() -> Self {
+        Self {
+            survive: vec![2],
+            born: vec![2],
+            max_age: None,
+        }
+    }
+}
+
+/// The most species `next_generation_species` can tell apart.
+pub const MAX_SPECIES: usize = 4;
+
+/// One set of `LifeRules` per species, indexed by species. Species 0 is the one every
+/// cell belongs to unless painted otherwise.
+pub struct SpeciesRules(pub Vec<LifeRules>);
+
+impl Default for SpeciesRules {
+    fn default() -> Self {
+        Self(vec![
+            LifeRules::default(),
+            LifeRules {
+                survive: vec![2, 3],
+                born: vec![2],
+                max_age: None,
+            },
+        ])
+    }
+}
+
+/// The living set one generation after `alive` on an unbounded board.
+pub fn next_generation(
+    alive: &HashSet<AxialCoordinate>,
+    rules: &LifeRules,
+) -> HashSet<AxialCoordinate> {
+    next_generation_with(alive, rules, Neighbourhood::Ring1, |coord| coord)
+}
+
+/// Like `next_generation`, but counting neighbours over `neighbourhood`, and every
+/// neighbour is passed through `resolve` first so that boards with wrapped edges count
+/// neighbours across the seam.
+pub fn next_generation_with(
+    alive: &HashSet<AxialCoordinate>,
+    rules: &LifeRules,
+    neighbourhood: Neighbourhood,
+    resolve: impl Fn(AxialCoordinate) -> AxialCoordinate,
+) -> HashSet<AxialCoordinate> {
+    // Every living cell adds itself to the count of each of its neighbours, alive or
+    // dead, so only cells next to life are ever looked at.
+    let mut alive_neighbours: HashMap<AxialCoordinate, u8> = HashMap::default();
+    for cell in alive.iter() {
+        for neighbour in cell.neighbours(neighbourhood) {
+            *alive_neighbours.entry(resolve(neighbour)).or_insert(0) += 1;
+        }
+    }
+    let mut next: HashSet<AxialCoordinate> = alive_neighbours
+        .iter()
+        .filter(|(cell, n)| {
+            if alive.contains(cell) {
+                rules.survive.contains(n)
+            } else {
+                rules.born.contains(n)
+            }
+        })
+        .map(|(cell, _)| *cell)
+        .collect();
+    // Living cells without any living neighbours never made it into the counts.
+    if rules.survive.contains(&0) {
+        next.extend(
+            alive
+                .iter()
+                .filter(|cell| !alive_neighbours.contains_key(cell)),
+        );
+    }
+    next
+}
+
+/// Ages for the cells of `next`, given the ages in the generation before it. Cells
+/// that survived are one generation older; newborn cells are left out and count as 0.
+pub fn next_ages(
+    ages: &HashMap<AxialCoordinate, u32>,
+    alive: &HashSet<AxialCoordinate>,
+    next: &HashSet<AxialCoordinate>,
+) -> HashMap<AxialCoordinate, u32> {
+    next.iter()
+        .filter(|cell| alive.contains(cell))
+        .map(|cell| (*cell, ages.get(cell).copied().unwrap_or(0) + 1))
+        .collect()
+}
+
+/// Kills the cells of `next` that were already alive in `alive` at an age of at least
+/// their species' `max_age`. `ages` and `species` describe `alive`, with missing cells
+/// counting as age 0 and species 0, as for `next_generation_species`.
+pub fn apply_max_age(
+    next: &mut HashSet<AxialCoordinate>,
+    alive: &HashSet<AxialCoordinate>,
+    ages: &HashMap<AxialCoordinate, u32>,
+    species: &HashMap<AxialCoordinate, u8>,
+    rules: &[LifeRules],
+) {
+    if rules.iter().all(|rules| rules.max_age.is_none()) {
+        return;
+    }
+    next.retain(|cell| {
+        if !alive.contains(cell) {
+            return true;
+        }
+        let kind = (species.get(cell).copied().unwrap_or(0) as usize).min(rules.len() - 1);
+        rules[kind]
+            .max_age
+            .is_none_or(|max_age| ages.get(cell).copied().unwrap_or(0) < max_age)
+    });
+}
+
+/// Whether each of the six hexes adjacent to `coord` is in `alive`, after passing it
+/// through `resolve` as `next_generation_with` does.
+///
+/// The entries follow `neighbour_iter`: the axial offsets (1, 0), (1, -1), (0, -1),
+/// (-1, 0), (-1, 1) and (0, 1). On screen that runs clockwise, starting at the upper
+/// right neighbour for flat-top hexes and at the right one for pointy-top hexes.
+pub fn neighbour_states(
+    coord: &AxialCoordinate,
+    alive: &HashSet<AxialCoordinate>,
+    resolve: impl Fn(AxialCoordinate) -> AxialCoordinate,
+) -> [bool; 6] {
+    let mut states = [false; 6];
+    for (state, neighbour) in states.iter_mut().zip(coord.neighbour_iter()) {
+        *state = alive.contains(&resolve(neighbour));
+    }
+    states
+}
+
+/// A hash of `alive` that does not depend on the set's iteration order, so equal
+/// living sets always hash the same.
+pub fn state_hash(alive: &HashSet<AxialCoordinate>) -> u64 {
+    let mut cells: Vec<(i32, i32)> = alive.iter().map(|cell| (cell.q(), cell.r())).collect();
+    cells.sort_unstable();
+    let mut hasher = DefaultHasher::new();
+    cells.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Like `next_generation_with`, for a board where each living cell belongs to a
+/// species. `species` maps cells to their species, with missing cells counting as
+/// species 0, and `rules[k]` applies to species `k`.
+///
+/// Cells only count neighbours of their own species. A living cell survives as its
+/// own species; a dead cell is born as the species with the most neighbours among
+/// those whose birth rule matches, ties going to the lower species. Returns the next
+/// living set and the species of its cells, leaving out species 0.
+pub fn next_generation_species(
+    alive: &HashSet<AxialCoordinate>,
+    species: &HashMap<AxialCoordinate, u8>,
+    rules: &[LifeRules],
+    neighbourhood: Neighbourhood,
+    resolve: impl Fn(AxialCoordinate) -> AxialCoordinate,
+) -> (HashSet<AxialCoordinate>, HashMap<AxialCoordinate, u8>) {
+    debug_assert!(
+        rules.len() <= MAX_SPECIES,
+        "at most {} species",
+        MAX_SPECIES
+    );
+    let species_of = |cell: &AxialCoordinate| {
+        (species.get(cell).copied().unwrap_or(0) as usize).min(rules.len() - 1)
+    };
+    let mut alive_neighbours: HashMap<AxialCoordinate, [u8; MAX_SPECIES]> = HashMap::default();
+    for cell in alive.iter() {
+        let kind = species_of(cell);
+        for neighbour in cell.neighbours(neighbourhood) {
+            alive_neighbours
+                .entry(resolve(neighbour))
+                .or_insert([0; MAX_SPECIES])[kind] += 1;
+        }
+    }
+    let mut next = HashMap::default();
+    for (cell, counts) in alive_neighbours.iter() {
+        if alive.contains(cell) {
+            let kind = species_of(cell);
+            if rules[kind].survive.contains(&counts[kind]) {
+                next.insert(*cell, kind);
+            }
+        } else if let Some(kind) = (0..rules.len())
+            .filter(|kind| rules[*kind].born.contains(&counts[*kind]))
+            .max_by_key(|kind| (counts[*kind], std::cmp::Reverse(*kind)))
+        {
+            next.insert(*cell, kind);
+        }
+    }
+    // Living cells without any living neighbours never made it into the counts.
+    for cell in alive
+        .iter()
+        .filter(|cell| !alive_neighbours.contains_key(cell))
+    {
+        let kind = species_of(cell);
+        if rules[kind].survive.contains(&0) {
+            next.insert(*cell, kind);
+        }
+    }
+    let next_species = next
+        .iter()
+        .filter(|(_, kind)| **kind != 0)
+        .map(|(cell, kind)| (*cell, *kind as u8))
+        .collect();
+    (next.into_keys().collect(), next_species)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pattern::builtin_pattern;
+
+    fn cells(coords: &[(i32, i32)]) -> HashSet<AxialCoordinate> {
+        coords
+            .iter()
+            .map(|(q, r)| AxialCoordinate::new(*q, *r))
+            .collect()
+    }
+
+    /// `alive` after `generations` ticks of `rules`.
+    fn step(
+        alive: &HashSet<AxialCoordinate>,
+        rules: &LifeRules,
+        generations: usize,
+    ) -> HashSet<AxialCoordinate> {
+        (0..generations).fold(alive.clone(), |alive, _| next_generation(&alive, rules))
+    }
+
+    /// Whether `name` comes back to its starting state after exactly `period` ticks
+    /// under the default rules, and not before.
+    fn assert_period(name: &str, period: usize) {
+        let start: HashSet<AxialCoordinate> = builtin_pattern(name).unwrap().into_iter().collect();
+        let rules = LifeRules::default();
+        for generations in 1..period {
+            assert!(
+                step(&start, &rules, generations) != start,
+                "{} repeated after {} generations",
+                name,
+                generations
+            );
+        }
+        assert!(
+            step(&start, &rules, period) == start,
+            "{} is not period {}",
+            name,
+            period
+        );
+    }
+
+    #[test]
+    fn ring_is_still_life_under_b3_s2() {
+        // Every ring cell has two living neighbours, the center six and the hexes
+        // outside at most two.
+        let ring: HashSet<AxialCoordinate> = AxialCoordinate::new(0, 0).ring(1).collect();
+        let rules = LifeRules {
+            survive: vec![2],
+            born: vec![3],
+            max_age: None,
+        };
+        assert!(next_generation(&ring, &rules) == ring);
+        assert!(step(&ring, &rules, 10) == ring);
+    }
+
+    #[test]
+    fn blinker_has_period_2() {
+        assert_period("blinker", 2);
+    }
+
+    #[test]
+    fn clock_has_period_4() {
+        assert_period("clock", 4);
+    }
+
+    #[test]
+    fn glider_moves_two_hexes_every_three_generations() {
+        let start: HashSet<AxialCoordinate> =
+            builtin_pattern("glider").unwrap().into_iter().collect();
+        let shifted: HashSet<AxialCoordinate> = start
+            .iter()
+            .map(|cell| AxialCoordinate::new(cell.q() - 2, cell.r() + 1))
+            .collect();
+        assert!(step(&start, &LifeRules::default(), 3) == shifted);
+    }
+
+    #[test]
+    fn next_generation_of_small_configurations() {
+        let rules = LifeRules::default();
+        assert!(next_generation(&cells(&[(0, 0)]), &rules).is_empty());
+        // Each cell of the pair has one neighbour and dies; the two hexes touching both
+        // have two and are born.
+        assert!(next_generation(&cells(&[(0, 0), (1, 0)]), &rules) == cells(&[(0, 1), (1, -1)]));
+        // The lone cell far from the pair changes nothing about it.
+        assert!(
+            next_generation(&cells(&[(0, 0), (1, 0), (10, 10)]), &rules)
+                == cells(&[(0, 1), (1, -1)])
+        );
+    }
+
+    #[test]
+    fn isolated_cells_die_out() {
+        let scattered = cells(&[(0, 0), (5, 0), (0, 5), (-5, 5)]);
+        assert!(next_generation(&scattered, &LifeRules::default()).is_empty());
+        assert!(next_generation(&HashSet::default(), &LifeRules::default()).is_empty());
+    }
+}
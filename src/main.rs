@@ -1,306 +1,2848 @@
+// Bevy systems take their resources and queries as arguments.
+#![allow(clippy::too_many_arguments)]
+
 use bevy::{
-    core::FixedTimestep,
     diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
-    math::Mat2,
+    ecs::system::SystemParam,
+    input::mouse::{MouseScrollUnit, MouseWheel},
     prelude::*,
-    utils::HashMap,
+    utils::{HashMap, HashSet},
 };
 use bevy_smud::prelude::*;
+use hex_of_life::{hex::*, life::*, pattern, picking::HexClicked};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{collections::VecDeque, marker::PhantomData};
+
+mod hud;
+mod minimap;
+mod screenshot;
+#[cfg(feature = "session")]
+mod session;
+mod sound;
 
 const CAMERA_SPEED_PER_SEC: f32 = 2.0;
-const SQRT_3: f32 = 1.732_050_8;
+const MIN_SIM_PERIOD: f32 = 0.01;
+const MAX_SIM_PERIOD: f32 = 5.0;
+// Radius of the disk of hexes set alive by a shift+click.
+const SELECT_RADIUS: i32 = 3;
+// Most hexes an alt+click flood select will take in.
+const MAX_FLOOD_SELECT: usize = 10_000;
+const MAX_BRUSH_SIZE: i32 = 10;
+// Projection scale of the main camera at startup and after resetting the view.
+const DEFAULT_ZOOM: f32 = 1.0;
+const MIN_ZOOM: f32 = 0.1;
+const MAX_ZOOM: f32 = 20.0;
+// World units per second at a projection scale of 1.
+const CAMERA_PAN_SPEED: f32 = 400.;
+// Change in the log of the zoom per mouse wheel notch.
+const WHEEL_ZOOM_PER_LINE: f32 = 0.15;
+// Trackpads scroll in pixels; this many count as one wheel notch.
+const WHEEL_PIXELS_PER_LINE: f32 = 50.;
+// Hex radius the SDF and its frame are built for; other sizes scale the transform.
 const HEX_SIZE: f32 = 10.;
+const MIN_HEX_SIZE: f32 = 4.;
+const MAX_HEX_SIZE: f32 = 40.;
+// Above this many hexes in view only the living ones are spawned.
+const MAX_STREAMED_HEXES: i32 = 50_000;
+const PATTERN_FILE: &str = "pattern.hex";
+const RLE_FILE: &str = "pattern.rle";
+const HISTORY_CAPACITY: usize = 50;
+// Coordinate labels are only drawn when zoomed in further than this projection scale.
+const LABEL_MAX_SCALE: f32 = 0.5;
+const LABEL_FONT: &str = "fonts/NotoSans-Medium.ttf";
+// Radius of the hexagon randomized by `--headless` runs.
+const HEADLESS_RADIUS: u32 = 50;
+// Living cells fade from bright to dark over this many generations.
+const AGE_GRADIENT_GENERATIONS: u32 = 10;
+// How long born cells take to fade in.
+const FADE_SECONDS: f32 = 0.25;
+// Seconds the trail of a cell killed by a tick takes to fade, and the trail value
+// below which it is dropped.
+const TRAIL_SECONDS: f32 = 1.5;
+const TRAIL_CUTOFF: f32 = 0.02;
+// Angular speed of the camera tour in radians per second.
+const TOUR_SPEED: f32 = 0.2;
+// Radius of the camera tour's circle, in hexes.
+const TOUR_RADIUS: f32 = 10.;
+// Color of the outline around the keyboard cursor's hex.
+const CURSOR_COLOR: Color = Color::rgb(1.0, 0.6, 0.0);
+// How close, in hexes, the keyboard cursor may get to the window edge before the view
+// scrolls after it.
+const CURSOR_MARGIN: f32 = 3.;
+// Color locked hexes are blended halfway towards.
+const LOCKED_TINT: Color = Color::rgb(0.2, 0.4, 1.0);
 
+/// The spawned hex entities by coordinate.
 #[derive(Default)]
 struct HexMap(HashMap<AxialCoordinate, Entity>);
 
+/// The SDF shader shared by every hex entity.
+struct HexShape(Handle<Shader>);
+
+/// The SDF shader for the outlines dead hexes are drawn as under themes with an
+/// `outline`, rebuilt whenever the theme or `HexInset` changes.
+#[derive(Default)]
+struct HexOutlineShape(Option<Handle<Shader>>);
+
+/// Inner radius of the drawn hexagons as a fraction of `HEX_SIZE`. At `SQRT_3 / 2`
+/// neighbouring hexes touch; smaller values leave a gap. 9 and 0 shrink and grow it.
+struct HexInset(f32);
+
+impl Default for HexInset {
+    fn default() -> Self {
+        Self(0.8)
+    }
+}
+
+/// How many generations each living cell has survived. Cells placed by hand or just
+/// born are missing from the map and count as age 0.
+#[derive(Default)]
+struct CellAges(HashMap<AxialCoordinate, u32>);
+
+/// Colors of the board. `hover` is added to the color of the hexes under the cursor.
+#[derive(Copy, Clone)]
+struct Theme {
+    name: &'static str,
+    alive: Color,
+    dead: Color,
+    background: Color,
+    hover: Color,
+    /// Width, in world units at the default hex size, of the border dead hexes are
+    /// drawn as in the dead color. `None` fills dead hexes like living ones.
+    outline: Option<f32>,
+}
+
+/// The themes T cycles through, starting with the first.
+const THEMES: [Theme; 4] = [
+    Theme {
+        name: "dark",
+        alive: Color::rgb(0.9, 0.9, 0.9),
+        dead: Color::BLACK,
+        background: Color::DARK_GRAY,
+        hover: Color::rgb(0.2, 0.2, 0.2),
+        outline: None,
+    },
+    Theme {
+        name: "high contrast",
+        alive: Color::YELLOW,
+        dead: Color::BLACK,
+        background: Color::WHITE,
+        hover: Color::rgb(0.3, 0.3, 0.5),
+        outline: None,
+    },
+    Theme {
+        name: "ocean",
+        alive: Color::rgb(0.4, 0.9, 1.0),
+        dead: Color::rgb(0.02, 0.05, 0.15),
+        background: Color::rgb(0.1, 0.15, 0.3),
+        hover: Color::rgb(0.15, 0.15, 0.1),
+        outline: None,
+    },
+    Theme {
+        name: "outlines",
+        alive: Color::rgb(0.9, 0.9, 0.9),
+        dead: Color::rgb(0.35, 0.35, 0.35),
+        background: Color::BLACK,
+        hover: Color::rgb(0.2, 0.2, 0.2),
+        outline: Some(1.5),
+    },
+];
+
+/// Hex colors for 0 to 6 living neighbours in `ColorMode::NeighbourCount`, from cold
+/// to hot. Counts past 6 use the last color.
+const NEIGHBOUR_COLORS: [Color; 7] = [
+    Color::rgb(0.1, 0.1, 0.3),
+    Color::rgb(0.1, 0.3, 0.8),
+    Color::rgb(0.1, 0.7, 0.7),
+    Color::rgb(0.2, 0.8, 0.2),
+    Color::rgb(0.9, 0.9, 0.2),
+    Color::rgb(0.9, 0.5, 0.1),
+    Color::rgb(0.9, 0.1, 0.1),
+];
+
+/// Colors of living cells of species 1 and up, species 0 being the theme's alive color.
+const SPECIES_COLORS: [Color; MAX_SPECIES - 1] = [
+    Color::rgb(1.0, 0.45, 0.35),
+    Color::rgb(0.45, 1.0, 0.45),
+    Color::rgb(0.55, 0.55, 1.0),
+];
+
+/// Radius in world units of every hex on the board. `,` and `.` shrink and grow it.
+struct HexSize(f32);
+
+impl Default for HexSize {
+    fn default() -> Self {
+        Self(HEX_SIZE)
+    }
+}
+
+/// The species of each living cell, see `next_generation_species`. Cells missing
+/// from the map are species 0.
+#[derive(Default)]
+struct CellSpecies(HashMap<AxialCoordinate, u8>);
+
+/// The species painted by the mouse. Keys 1 to 4 pick one.
+#[derive(Default)]
+struct SelectedSpecies(u8);
+
+/// The pattern most recently loaded with L or Shift+L, relative to the hex it is pasted at.
+/// Q and E rotate it, F mirrors it, V pastes it again.
+#[derive(Default)]
+struct PatternClipboard(Vec<AxialCoordinate>);
+
+/// The gamepad driving the camera: the first one connected, until it disconnects.
+#[derive(Default)]
+struct ActiveGamepad(Option<Gamepad>);
+
+/// Radius of the hexagon painted around the cursor, 0 for just the hex under it.
+/// Numpad + and - grow and shrink it.
+#[derive(Default)]
+struct BrushSize(i32);
+
+/// How clicks, paint strokes and hovering pick the hex under the cursor. With `ignore_gaps`
+/// set, the cursor only picks a hex while it is over the drawn hexagon rather than
+/// the gap around it. P toggles it.
+#[derive(Default)]
+struct PickingConfig {
+    ignore_gaps: bool,
+}
+
+/// The hexes picked by the last alt+click: every spawned hex connected to the clicked
+/// one that was alive or dead like it. Delete kills them, Insert fills them and
+/// Escape deselects them.
+#[derive(Default)]
+struct FloodSelection(HashSet<AxialCoordinate>);
+
+/// Hexes that keep their state: clicks and paint strokes pass over them and ticks
+/// leave them alive or dead as they were, though they still count as neighbours.
+/// X toggles the lock on the hovered hex.
+#[derive(Default)]
+struct LockedCells(HashSet<AxialCoordinate>);
+
+/// While `enabled`, the camera circles the living cells on its own and ignores the
+/// player. O toggles it.
+#[derive(Default)]
+struct CameraTour {
+    enabled: bool,
+    angle: f32,
+}
+
+/// The hex edited from the keyboard. While `KeyboardEditing` is on it is outlined, the
+/// numpad moves it and Return or Space toggles it.
+#[derive(Default)]
+struct CursorHex(AxialCoordinate);
+
+/// Whether the keyboard edits the board at `CursorHex`. K toggles it. Return and
+/// Space go back to running and stepping the simulation once it is off.
+#[derive(Default)]
+struct KeyboardEditing(bool);
+
+/// What the color of a hex shows. M switches between the modes.
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+enum ColorMode {
+    /// Living cells in their species' color, darkening with age, on dead cells.
+    #[default]
+    AliveDead,
+    /// The number of living neighbours of every hex, see `NEIGHBOUR_COLORS`. Dead
+    /// hexes are drawn darker than living ones.
+    NeighbourCount,
+}
+
+/// The spawned hex under the mouse cursor.
+#[derive(Default)]
+struct HoveredHex(Option<Entity>);
+
+/// Coordinates of every living cell. This is the simulation state; spawned hexes
+/// only mirror it through their `Alive` marker.
+#[derive(Default)]
+struct LivingCells(HashSet<AxialCoordinate>);
+
 struct GameState {
     pub started: bool,
+    /// Generations to simulate on the next frame, whether or not the game is running.
+    pub steps_requested: u32,
+    /// Stop running once a tick leaves the board unchanged.
+    pub pause_on_stable: bool,
+}
+
+/// The most living cells the simulation may produce. A tick that would exceed it is
+/// dropped and the simulation paused, so runaway growth can't exhaust memory.
+struct MaxPopulation(usize);
+
+impl Default for MaxPopulation {
+    fn default() -> Self {
+        Self(1_000_000)
+    }
+}
+
+/// Generations simulated by one Shift+Space.
+struct StepCount(u32);
+
+impl Default for StepCount {
+    fn default() -> Self {
+        Self(10)
+    }
+}
+
+/// The generation at which a running simulation pauses itself, if any. Pass
+/// `--stop-at <n>` at startup to set it.
+#[derive(Default)]
+struct GenerationLimit(Option<u64>);
+
+/// Sent by `game_of_life` after every tick with the cells that changed.
+struct GenerationAdvanced {
+    generation: u64,
+    births: Vec<AxialCoordinate>,
+    deaths: Vec<AxialCoordinate>,
+}
+
+/// Whether the last tick left the board unchanged.
+#[derive(Default)]
+struct Stable(bool);
+
+/// Hashes of the living sets of the last `capacity` generations, oldest first, for
+/// spotting oscillators.
+struct StateHistory {
+    hashes: VecDeque<u64>,
+    capacity: usize,
+}
+
+impl Default for StateHistory {
+    fn default() -> Self {
+        Self {
+            hashes: VecDeque::new(),
+            capacity: 16,
+        }
+    }
+}
+
+/// Period of the oscillator the board has settled into, if `StateHistory` holds one.
+/// Still lifes are reported through `Stable` instead.
+#[derive(Default)]
+struct OscillatorPeriod(Option<usize>);
+
+/// The board as it was before one simulation tick.
+struct SimSnapshot {
+    generation: u64,
+    living: HashSet<AxialCoordinate>,
+    ages: HashMap<AxialCoordinate, u32>,
+    species: HashMap<AxialCoordinate, u8>,
+}
+
+/// The boards before each of the last `capacity` ticks, oldest first, for stepping
+/// back with Backspace. Pass `--sim-history <n>` at startup to keep more or fewer.
+struct SimHistory {
+    snapshots: VecDeque<SimSnapshot>,
+    capacity: usize,
+}
+
+impl Default for SimHistory {
+    fn default() -> Self {
+        Self {
+            snapshots: VecDeque::new(),
+            capacity: 64,
+        }
+    }
+}
+
+/// Snapshots of `LivingCells` taken before each user edit, for undo and redo.
+/// Simulation ticks are not recorded.
+#[derive(Default)]
+struct HistoryBuffer {
+    undo: VecDeque<HashSet<AxialCoordinate>>,
+    redo: Vec<HashSet<AxialCoordinate>>,
+}
+
+impl HistoryBuffer {
+    /// Records `cells` as the state to return to on undo, and forgets anything that
+    /// could be redone.
+    fn push(&mut self, cells: &HashSet<AxialCoordinate>) {
+        self.redo.clear();
+        if self.undo.back() == Some(cells) {
+            return;
+        }
+        if self.undo.len() == HISTORY_CAPACITY {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(cells.clone());
+    }
+}
+
+/// Seconds between generations while the simulation runs. Time accumulates while
+/// running and a generation is simulated for every full `period` that has built up,
+/// at most `max_steps_per_frame` of them in one frame. Time for the generations past
+/// that is dropped, so a board that can't keep up slows down instead of falling
+/// further behind.
+struct SimTimer {
+    accumulator: f32,
+    period: f32,
+    max_steps_per_frame: u32,
+}
+
+impl Default for SimTimer {
+    fn default() -> Self {
+        Self {
+            accumulator: 0.,
+            period: 0.5,
+            max_steps_per_frame: 4,
+        }
+    }
+}
+
+/// Number of generations simulated since the board was last cleared or randomized.
+#[derive(Default)]
+struct Generation(u64);
+
+/// Seed for the random number generator used to randomize the board. Random by
+/// default; pass `--seed <n>` at startup to make runs reproducible.
+struct RngSeed(u64);
+
+/// The generator every randomization system draws from, seeded from `RngSeed`.
+struct BoardRng(StdRng);
+
+struct Args {
+    seed: Option<u64>,
+    headless: Option<u64>,
+    shape: BoardShape,
+    wrap: Option<(i32, i32)>,
+    orientation: HexOrientation,
+    log_diagnostics: bool,
+    sim_history: usize,
+    pattern: Option<String>,
+    sim_bounds: Option<AxialRect>,
+    stop_at: Option<u64>,
+    origin: AxialCoordinate,
+    max_age: Option<u32>,
+}
+
+impl Args {
+    fn parse() -> Self {
+        let mut args = Args {
+            seed: None,
+            headless: None,
+            shape: BoardShape::Unbounded,
+            wrap: None,
+            orientation: HexOrientation::FlatTop,
+            log_diagnostics: false,
+            sim_history: SimHistory::default().capacity,
+            pattern: None,
+            sim_bounds: None,
+            stop_at: None,
+            origin: AxialCoordinate::default(),
+            max_age: None,
+        };
+        let mut iter = std::env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--seed" => args.seed = iter.next().and_then(|s| s.parse().ok()),
+                "--headless" => args.headless = iter.next().and_then(|s| s.parse().ok()),
+                "--hexagon" => {
+                    if let Some(radius) = iter.next().and_then(|s| s.parse().ok()) {
+                        args.shape = BoardShape::Hexagon { radius };
+                    }
+                }
+                "--pointy" => args.orientation = HexOrientation::PointyTop,
+                "--log-diagnostics" => args.log_diagnostics = true,
+                "--pattern" => args.pattern = iter.next(),
+                "--origin" => {
+                    let q = iter.next().and_then(|s| s.parse().ok());
+                    let r = iter.next().and_then(|s| s.parse().ok());
+                    if let (Some(q), Some(r)) = (q, r) {
+                        args.origin = AxialCoordinate::new(q, r);
+                    }
+                }
+                "--stop-at" => args.stop_at = iter.next().and_then(|s| s.parse().ok()),
+                "--max-age" => args.max_age = iter.next().and_then(|s| s.parse().ok()),
+                "--sim-bounds" => {
+                    let bounds: Vec<i32> = (0..4)
+                        .filter_map(|_| iter.next().and_then(|s| s.parse().ok()))
+                        .collect();
+                    if let [q0, r0, q1, r1] = bounds[..] {
+                        args.sim_bounds = Some(AxialRect {
+                            min: IVec2::new(q0.min(q1), r0.min(r1)),
+                            max: IVec2::new(q0.max(q1), r0.max(r1)),
+                        });
+                    }
+                }
+                "--sim-history" => {
+                    if let Some(capacity) = iter.next().and_then(|s| s.parse().ok()) {
+                        args.sim_history = capacity;
+                    }
+                }
+                "--rectangle" => {
+                    let cols = iter.next().and_then(|s| s.parse().ok());
+                    let rows = iter.next().and_then(|s| s.parse().ok());
+                    if let (Some(cols), Some(rows)) = (cols, rows) {
+                        args.shape = BoardShape::Rectangle { cols, rows };
+                    }
+                }
+                "--wrap" => {
+                    let width = iter.next().and_then(|s| s.parse().ok());
+                    let height = iter.next().and_then(|s| s.parse().ok());
+                    match (width, height) {
+                        (Some(width), Some(height))
+                            if width > 0 && width % 2 == 0 && height > 0 =>
+                        {
+                            args.wrap = Some((width, height));
+                        }
+                        (Some(_), Some(_)) => {
+                            eprintln!("--wrap needs an even width and a positive height")
+                        }
+                        _ => {}
+                    }
+                }
+                _ => eprintln!("unrecognized argument: {}", arg),
+            }
+        }
+        args
+    }
+}
+
+/// Probability that a hex comes alive when the board is randomized.
+struct SpawnConfig {
+    density: f32,
+}
+
+impl Default for SpawnConfig {
+    fn default() -> Self {
+        Self { density: 0.3 }
+    }
+}
+
+/// Bounds for the orthographic projection scale of the main camera.
+struct CameraLimits {
+    min_zoom: f32,
+    max_zoom: f32,
+}
+
+impl Default for CameraLimits {
+    fn default() -> Self {
+        Self {
+            min_zoom: MIN_ZOOM,
+            max_zoom: MAX_ZOOM,
+        }
+    }
+}
+
+/// The region of the plane that makes up the board. Pass `--hexagon <radius>` or
+/// `--rectangle <cols> <rows>` at startup to bound it. Only the hexes in view are ever
+/// spawned, whatever the shape.
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+enum BoardShape {
+    #[default]
+    Unbounded,
+    /// The hexes within `radius` steps of the origin. Cells outside are always dead.
+    Hexagon { radius: i32 },
+    /// `cols` by `rows` hexes in odd-q offset space, centered on the origin.
+    Rectangle { cols: i32, rows: i32 },
+}
+
+/// How the edges of the board connect. With `wrap` set to `Some((width, height))`
+/// the board is a torus of that many columns and rows, see `AxialCoordinate::wrap`.
+/// The shape and the torus are centered on `origin`, which the camera also starts on.
+/// Pass `--wrap <width> <height>` at startup to wrap the board and `--origin <q> <r>`
+/// to move it.
+#[derive(Default)]
+struct BoardTopology {
+    wrap: Option<(i32, i32)>,
+    shape: BoardShape,
+    origin: AxialCoordinate,
+}
+
+impl BoardTopology {
+    /// Whether `coord` is a cell of the board, rather than an alias of one across
+    /// a wrapped edge or a hex outside its shape.
+    fn contains(&self, coord: &AxialCoordinate) -> bool {
+        let local = self.localize(*coord);
+        let in_shape = match self.shape {
+            BoardShape::Unbounded => true,
+            BoardShape::Hexagon { radius } => coord.distance(&self.origin) <= radius,
+            BoardShape::Rectangle { cols, rows } => {
+                let (col, row) = axial_to_offset(&local);
+                (-cols / 2..cols - cols / 2).contains(&col)
+                    && (-rows / 2..rows - rows / 2).contains(&row)
+            }
+        };
+        in_shape && self.resolve(*coord) == *coord
+    }
+
+    /// The board cell that `coord` refers to.
+    fn resolve(&self, coord: AxialCoordinate) -> AxialCoordinate {
+        match self.wrap {
+            Some((width, height)) => self.globalize(self.localize(coord).wrap(width, height)),
+            None => coord,
+        }
+    }
+
+    /// `coord` as seen from a rectangle or torus centered on the axial origin. The
+    /// shift happens in offset space so that the rectangle keeps its shape wherever
+    /// `origin` is.
+    fn localize(&self, coord: AxialCoordinate) -> AxialCoordinate {
+        let (col, row) = axial_to_offset(&coord);
+        let (origin_col, origin_row) = axial_to_offset(&self.origin);
+        offset_to_axial(col - origin_col, row - origin_row)
+    }
+
+    /// The inverse of `localize`.
+    fn globalize(&self, local: AxialCoordinate) -> AxialCoordinate {
+        let (col, row) = axial_to_offset(&local);
+        let (origin_col, origin_row) = axial_to_offset(&self.origin);
+        offset_to_axial(col + origin_col, row + origin_row)
+    }
+}
+
+/// An axis-aligned rectangle in axial space, `min` and `max` included.
+#[derive(Copy, Clone)]
+struct AxialRect {
+    min: IVec2,
+    max: IVec2,
 }
 
+impl AxialRect {
+    fn contains(&self, coord: &AxialCoordinate) -> bool {
+        coord.0.cmpge(self.min).all() && coord.0.cmple(self.max).all()
+    }
+}
+
+/// The part of the board the simulation runs in. Cells outside it stay as they are,
+/// though cells inside still count them as neighbours. Pass `--sim-bounds <q0> <r0>
+/// <q1> <r1>` at startup to set it.
+#[derive(Default)]
+struct SimBounds(Option<AxialRect>);
+
 fn main() {
-    App::new()
-        .insert_resource(Msaa { samples: 4 })
-        .insert_resource(ClearColor(Color::DARK_GRAY))
-        .insert_resource(GameState { started: false })
+    let args = Args::parse();
+    let seed = args.seed.unwrap_or_else(rand::random);
+    let initial_cells = match args
+        .pattern
+        .as_deref()
+        .map(|name| (name, pattern::builtin_pattern(name)))
+    {
+        Some((_, Some(cells))) => cells
+            .into_iter()
+            .map(|cell| AxialCoordinate(cell.0 + args.origin.0))
+            .collect(),
+        Some((name, None)) => {
+            // Logging isn't set up until the app is built.
+            eprintln!(
+                "unknown pattern {:?}, expected one of: {}",
+                name,
+                pattern::BUILTIN_PATTERNS.join(", ")
+            );
+            HashSet::default()
+        }
+        None => HashSet::default(),
+    };
+    let topology = BoardTopology {
+        wrap: args.wrap,
+        shape: args.shape,
+        origin: args.origin,
+    };
+    if let Some(generations) = args.headless {
+        run_headless(generations, seed, &topology);
+        return;
+    }
+    // `--max-age <n>` makes every species die once its cells reach that age.
+    let mut rules = SpeciesRules::default();
+    for rules in rules.0.iter_mut() {
+        rules.max_age = args.max_age;
+    }
+    let mut app = App::new();
+    app.insert_resource(Msaa { samples: 4 })
+        .insert_resource(THEMES[0])
+        .insert_resource(GameState {
+            started: false,
+            steps_requested: 0,
+            pause_on_stable: true,
+        })
+        .init_resource::<Stable>()
+        .add_event::<GenerationAdvanced>()
+        .add_event::<HexClicked>()
+        .init_resource::<MaxPopulation>()
+        .init_resource::<StepCount>()
+        .insert_resource(GenerationLimit(args.stop_at))
+        .init_resource::<StateHistory>()
+        .insert_resource(SimHistory {
+            capacity: args.sim_history,
+            ..Default::default()
+        })
+        .init_resource::<OscillatorPeriod>()
+        .init_resource::<SimTimer>()
+        .init_resource::<Generation>()
+        .insert_resource(rules)
+        .insert_resource(topology)
+        .insert_resource(SimBounds(args.sim_bounds))
+        // `--pointy` draws the board with pointy-top hexes.
+        .insert_resource(args.orientation)
+        .init_resource::<Neighbourhood>()
+        .init_resource::<HexSize>()
+        .init_resource::<HexInset>()
+        .insert_resource(RngSeed(seed))
+        .insert_resource(BoardRng(StdRng::seed_from_u64(seed)))
+        .init_resource::<SpawnConfig>()
+        .init_resource::<CameraLimits>()
         .init_resource::<HexMap>()
+        .init_resource::<HexOutlineShape>()
+        .insert_resource(LivingCells(initial_cells))
+        .init_resource::<CellAges>()
+        .init_resource::<CellSpecies>()
+        .init_resource::<SelectedSpecies>()
+        .init_resource::<BrushSize>()
+        .init_resource::<PickingConfig>()
+        .init_resource::<HistoryBuffer>()
+        .init_resource::<PatternClipboard>()
+        .init_resource::<FloodSelection>()
+        .init_resource::<LockedCells>()
+        .init_resource::<HoveredHex>()
+        .init_resource::<ActiveGamepad>()
+        .init_resource::<CameraTour>()
+        .init_resource::<ColorMode>()
+        .init_resource::<CursorHex>()
+        .init_resource::<KeyboardEditing>()
         .add_plugins(DefaultPlugins)
         .add_plugin(SmudPlugin)
-        .add_plugin(LogDiagnosticsPlugin::default())
-        // .add_plugin(FrameTimeDiagnosticsPlugin::default())
+        .add_plugin(hud::HudPlugin)
+        .add_plugin(minimap::MinimapPlugin)
+        .add_plugin(screenshot::ScreenshotPlugin)
+        .add_plugin(sound::SoundPlugin)
+        .add_plugin(FrameTimeDiagnosticsPlugin)
         .add_startup_system(setup_system)
         .add_system(player_camera_control)
-        .add_system(highlight_hex)
-        .add_system(color_hex)
-        .add_system(game_control)
-        .add_system_set(
-            SystemSet::new()
-                .with_run_criteria(FixedTimestep::step(0.5))
-                .with_system(game_of_life),
+        .add_system(drag_camera)
+        .add_system(wheel_zoom)
+        .add_system(gamepad_connections)
+        .add_system(gamepad_camera_control)
+        .add_system(center_camera)
+        .add_system(camera_tour)
+        // Edits land on the board the tick left behind, so a click in the same frame as a
+        // tick is neither overwritten by it nor counted towards it.
+        .add_system(game_of_life.label("simulation"))
+        .add_system(
+            paint_hex
+                .label("paint")
+                .after("simulation")
+                .after("picking"),
         )
-        .run();
+        .add_system(pick_hex.label("picking"))
+        // After the stroke's undo snapshot is taken, so undoing a click reverts it.
+        .add_system(highlight_hex.after("paint").after("picking"))
+        .add_system(flood_select.after("picking"))
+        .add_system(edit_selection.after("simulation"))
+        .add_system(lock_hex)
+        .add_system(track_hover)
+        .add_system(game_control)
+        .add_system(clear_board.after("simulation"))
+        .add_system(randomize_board.after("simulation"))
+        .add_system(invert_board.after("simulation"))
+        .add_system(density_control)
+        .add_system(export_pattern)
+        .add_system(import_pattern.after("simulation"))
+        .add_system(rotate_clipboard)
+        .add_system(reflect_clipboard)
+        .add_system(undo_redo.after("simulation"))
+        .add_system(keyboard_editing.after("simulation"))
+        .add_system(follow_cursor_hex)
+        .add_system(draw_cursor_marker)
+        .add_system(step_back.after("simulation"))
+        .add_system(log_generation)
+        .add_system(start_color_tweens)
+        .add_system(decay_trails)
+        .add_system(hex_size_control)
+        .add_system(theme_control)
+        .add_system(color_mode_control)
+        .add_system(species_control)
+        .add_system(neighbourhood_control)
+        .add_system(brush_control)
+        .add_system(picking_control)
+        .add_system(rescale_hexes)
+        .add_system(hex_inset_control)
+        .add_system(rebuild_hex_shape.before("streaming"))
+        .add_system(visible_hex_streaming.label("streaming"))
+        .add_system(coordinate_overlay)
+        .add_system_to_stage(CoreStage::PostUpdate, sync_alive)
+        // After `sync_alive`'s commands are applied, so its removals are still visible.
+        .add_system_to_stage(CoreStage::Last, color_hex)
+        .add_system_to_stage(CoreStage::Last, outline_dead_hexes)
+        .add_system_to_stage(CoreStage::Last, advance_color_tweens);
+    if args.log_diagnostics {
+        app.add_plugin(LogDiagnosticsPlugin::default());
+    }
+    // A pattern asked for on the command line wins over the last session.
+    #[cfg(feature = "session")]
+    app.add_plugin(session::SessionPlugin {
+        autoload: args.pattern.is_none(),
+    });
+    app.run();
 }
 
 #[derive(Component)]
 struct MainCamera;
+
+/// The outline drawn around `CursorHex`.
+#[derive(Component)]
+struct CursorMarker;
+/// Marks the hex of a living cell. Hexes without it are dead; `sync_alive` keeps it in
+/// line with `LivingCells`.
 #[derive(Component)]
 struct Alive;
+/// Mirror of the cell's entry in `CellAges`, present alongside `Alive`.
+#[derive(Component)]
+struct Age(u32);
+/// Mirror of the cell's entry in `CellSpecies`, present alongside `Alive`.
 #[derive(Component)]
-struct Dead;
+struct Species(u8);
+/// A fade from `from` to `to` over `FADE_SECONDS` that a hex is going through after
+/// being born. `color_hex` leaves hexes alone while they have one.
+#[derive(Component)]
+struct ColorTween {
+    from: Color,
+    to: Color,
+    elapsed: f32,
+}
+/// How recently a dead hex was killed by a tick, from 1 right after to 0 once it has
+/// faded to the dead color over `TRAIL_SECONDS`.
+#[derive(Component)]
+struct Trail(f32);
+/// The components `hex_color` reads from a living hex.
+type LivingCell<'a> = (&'a Age, &'a Species);
+/// The components `color_hex` reads from any hex.
+type HexLook<'a> = (
+    &'a AxialCoordinate,
+    Option<LivingCell<'a>>,
+    Option<&'a Trail>,
+);
 
-fn game_control(buttons: Res<Input<KeyCode>>, mut game_state: ResMut<GameState>) {
-    if !buttons.just_pressed(KeyCode::Return) {
-        return;
+fn game_control(
+    buttons: Res<Input<KeyCode>>,
+    editing: Res<KeyboardEditing>,
+    step_count: Res<StepCount>,
+    mut game_state: ResMut<GameState>,
+    mut timer: ResMut<SimTimer>,
+) {
+    // Return and Space edit the cursor hex instead while editing from the keyboard.
+    if buttons.just_pressed(KeyCode::Return) && !editing.0 {
+        game_state.started = !game_state.started;
+    }
+    if buttons.just_pressed(KeyCode::Space) && !editing.0 {
+        // Shift+Space skips ahead several generations at once.
+        game_state.steps_requested =
+            if buttons.pressed(KeyCode::LShift) || buttons.pressed(KeyCode::RShift) {
+                step_count.0
+            } else {
+                1
+            };
+    }
+    let mut period = timer.period;
+    if buttons.just_pressed(KeyCode::Minus) {
+        period /= 2.;
+    }
+    if buttons.just_pressed(KeyCode::Equals) {
+        period *= 2.;
+    }
+    if period != timer.period {
+        timer.period = period.clamp(MIN_SIM_PERIOD, MAX_SIM_PERIOD);
+        info!("generation period: {}s", timer.period);
     }
-    game_state.started = !game_state.started;
 }
 
-fn game_of_life(
+/// Restores the board from before the last tick on Backspace while paused. Life can't
+/// be run backwards, so this only reaches as far back as `SimHistory` remembers.
+fn step_back(
+    keys: Res<Input<KeyCode>>,
     game_state: Res<GameState>,
-    mut query: Query<(Entity, &mut SmudShape, &Transform), With<Alive>>,
-    mut commands: Commands,
-    hex_map: Res<HexMap>,
+    mut sim_history: ResMut<SimHistory>,
+    mut state_history: ResMut<StateHistory>,
+    mut generation: ResMut<Generation>,
+    mut living: ResMut<LivingCells>,
+    mut ages: ResMut<CellAges>,
+    mut species: ResMut<CellSpecies>,
 ) {
-    if !game_state.started {
-        return;
-    }
-    let mut dead_alive_neighbours: HashMap<Entity, u8> = HashMap::default();
-    for (entity, _shape, trans) in query.iter() {
-        let cube_float = screen_to_cube_float(HEX_SIZE, trans.translation.truncate());
-        let cube = cube_round(cube_float);
-        let axial: AxialCoordinate = cube.into();
-        let mut alive_alive_neighbours = 0;
-        for axial_neighbour in axial.neighbour_iter() {
-            // Lookup neighbours (maybe just put them in as component?) and check if they are alive
-            if let Some(entity_neighbour) = hex_map.0.get(&axial_neighbour) {
-                if query.get(*entity_neighbour).is_ok() {
-                    // This neighbour is alive and we should just count it to ourselves
-                    // as we will iterate over this one too.
-                    alive_alive_neighbours += 1;
-                } else {
-                    // This neighbour is dead so we add ourself to its alive neighbour count.
-                    let dead = dead_alive_neighbours.entry(*entity_neighbour).or_insert(0);
-                    *dead += 1;
-                }
-            }
-        }
-        if alive_alive_neighbours != 2 {
-            commands.entity(entity).remove::<Alive>();
-        }
+    if !keys.just_pressed(KeyCode::Back) || game_state.started {
+        return;
     }
-    for (e, n) in dead_alive_neighbours {
-        if n == 2 {
-            commands.entity(e).insert(Alive);
+    let snapshot = match sim_history.snapshots.pop_back() {
+        Some(snapshot) => snapshot,
+        None => {
+            warn!("no earlier generation to step back to");
+            return;
         }
-    }
+    };
+    // The stepped-back generation will be hashed again when it is simulated.
+    state_history.hashes.pop_back();
+    generation.0 = snapshot.generation;
+    living.0 = snapshot.living;
+    ages.0 = snapshot.ages;
+    species.0 = snapshot.species;
 }
 
-fn highlight_hex(
-    // need to get window dimensions
-    wnds: Res<Windows>,
-    buttons: Res<Input<MouseButton>>,
-    // query to get camera transform
-    q_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
-    mut q_alive: Query<(), With<Alive>>,
-    hex_map: Res<HexMap>,
-    mut commands: Commands,
+fn ctrl_held(keys: &Input<KeyCode>) -> bool {
+    keys.pressed(KeyCode::LControl) || keys.pressed(KeyCode::RControl)
+}
+
+fn alt_held(keys: &Input<KeyCode>) -> bool {
+    keys.pressed(KeyCode::LAlt) || keys.pressed(KeyCode::RAlt)
+}
+
+fn undo_redo(
+    keys: Res<Input<KeyCode>>,
+    mut history: ResMut<HistoryBuffer>,
+    mut living: ResMut<LivingCells>,
 ) {
-    if !buttons.just_pressed(MouseButton::Left) {
+    if !ctrl_held(&keys) {
         return;
     }
-    let (camera, camera_transform) = q_camera.single();
-    let wnd = wnds.get(camera.window).unwrap();
-    if let Some(screen_pos) = wnd.cursor_position() {
-        let window_size = Vec2::new(wnd.width() as f32, wnd.height() as f32);
-        // convert screen position [0..resolution] to ndc [-1..1] (gpu coordinates)
-        let ndc = (screen_pos / window_size) * 2.0 - Vec2::ONE;
-        // matrix for undoing the projection and camera transform
-        let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix.inverse();
-        // use it to convert ndc to world-space coordinates
-        let world_pos = ndc_to_world.project_point3(ndc.extend(-1.0));
-        // reduce it to a 2D value
-        let world_pos: Vec2 = world_pos.truncate();
-
-        // Make the coordinate into an axial coordinate for getting a hex by hashmap
-        let cube_float = screen_to_cube_float(HEX_SIZE, world_pos);
-        let cube = cube_round(cube_float);
-        let axial: AxialCoordinate = cube.into();
-        if let Some(hex_entity) = hex_map.0.get(&axial) {
-            if q_alive.get_mut(*hex_entity).is_ok() {
-                commands.entity(*hex_entity).remove::<Alive>();
-            } else {
-                commands.entity(*hex_entity).insert(Alive);
-            }
+    if keys.just_pressed(KeyCode::Z) {
+        if let Some(previous) = history.undo.pop_back() {
+            let current = std::mem::replace(&mut living.0, previous);
+            history.redo.push(current);
+        }
+    } else if keys.just_pressed(KeyCode::Y) {
+        if let Some(next) = history.redo.pop() {
+            let current = std::mem::replace(&mut living.0, next);
+            history.undo.push_back(current);
         }
     }
 }
 
-fn color_hex(mut query: Query<(&mut SmudShape, Option<&Alive>)>) {
-    for (mut hex, alive) in query.iter_mut() {
-        if alive.is_some() {
-            hex.color = Color::GRAY;
-        } else {
-            hex.color = Color::BLACK;
-        }
+fn clear_board(
+    buttons: Res<Input<KeyCode>>,
+    mut living: ResMut<LivingCells>,
+    mut history: ResMut<HistoryBuffer>,
+    mut generation: ResMut<Generation>,
+) {
+    if !buttons.just_pressed(KeyCode::C) {
+        return;
     }
+    history.push(&living.0);
+    living.0.clear();
+    generation.0 = 0;
 }
 
-fn player_camera_control(
-    kb: Res<Input<KeyCode>>,
-    time: Res<Time>,
-    mut query: Query<&mut OrthographicProjection, With<MainCamera>>,
+/// Flips every spawned hex between alive and dead on I.
+fn invert_board(
+    buttons: Res<Input<KeyCode>>,
+    hex_map: Res<HexMap>,
+    mut living: ResMut<LivingCells>,
+    mut species: ResMut<CellSpecies>,
+    mut history: ResMut<HistoryBuffer>,
 ) {
-    let dist = CAMERA_SPEED_PER_SEC * time.delta().as_secs_f32();
-
-    for mut projection in query.iter_mut() {
-        let mut log_scale = projection.scale.ln();
-
-        if kb.pressed(KeyCode::PageUp) {
-            log_scale -= dist;
-        }
-        if kb.pressed(KeyCode::PageDown) {
-            log_scale += dist;
+    if !buttons.just_pressed(KeyCode::I) {
+        return;
+    }
+    history.push(&living.0);
+    for coord in hex_map.0.keys() {
+        if !living.0.remove(coord) {
+            living.0.insert(*coord);
+            species.0.remove(coord);
         }
-
-        projection.scale = log_scale.exp();
     }
 }
 
-fn setup_system(
-    mut commands: Commands,
-    mut shaders: ResMut<Assets<Shader>>,
-    wnds: Res<Windows>,
-    mut hex_map: ResMut<HexMap>,
+fn randomize_board(
+    buttons: Res<Input<KeyCode>>,
+    seed: Res<RngSeed>,
+    spawn_config: Res<SpawnConfig>,
+    mut rng: ResMut<BoardRng>,
+    query: Query<&AxialCoordinate>,
+    mut living: ResMut<LivingCells>,
+    mut species: ResMut<CellSpecies>,
+    mut history: ResMut<HistoryBuffer>,
+    mut generation: ResMut<Generation>,
 ) {
-    let hexagon = shaders.add_sdf_expr("sd_hexagon(p, 8.)");
-    let mut half_width: f32 = 0.;
-    let mut half_height: f32 = 0.;
-    if let Some(wnd) = wnds.get_primary() {
-        half_width = wnd.width() / 2.;
-        half_height = wnd.height() / 2.;
+    if buttons.just_pressed(KeyCode::F5) {
+        // Restart the sequence so the same seed always produces the same board.
+        rng.0 = StdRng::seed_from_u64(seed.0);
+    } else if !buttons.just_pressed(KeyCode::R) {
+        return;
     }
+    history.push(&living.0);
+    species.0.clear();
+    // Entities come back in spawn order, which depends on how the camera has moved.
+    let mut coords: Vec<AxialCoordinate> = query.iter().copied().collect();
+    coords.sort_unstable_by_key(|coord| (coord.q(), coord.r()));
+    living.0 = random_cells(&mut rng.0, coords, spawn_config.density);
+    generation.0 = 0;
+}
 
-    commands
-        .spawn_bundle(OrthographicCameraBundle::new_2d())
-        .insert(MainCamera);
-    for q in -100..100 {
-        for r in -100..100 {
-            let coord = AxialCoordinate::new(q, r);
-            let center = axial_to_screen(HEX_SIZE, &coord);
-            if center.x < -half_width || center.x > half_width {
-                continue;
-            }
-            if center.y < -half_height || center.y > half_height {
-                continue;
-            }
-            let ent = commands.spawn_bundle(ShapeBundle {
-                shape: SmudShape {
-                    color: Color::BLACK,
-                    sdf: hexagon.clone(),
-                    frame: Frame::Quad(HEX_SIZE),
-                    fill: SIMPLE_FILL_HANDLE.typed(),
-                },
-                transform: Transform::from_translation((center, 0.).into()),
-                ..Default::default()
-            });
-            hex_map.0.insert(coord, ent.id().clone());
-        }
-    }
+/// The hexes of `coords` that come alive, each with probability `density`, drawing
+/// once from `rng` per hex in order.
+fn random_cells(
+    rng: &mut StdRng,
+    coords: impl IntoIterator<Item = AxialCoordinate>,
+    density: f32,
+) -> HashSet<AxialCoordinate> {
+    coords
+        .into_iter()
+        .filter(|_| rng.gen::<f32>() < density)
+        .collect()
 }
 
-struct HexDimensions {
-    width: f32,
-    height: f32,
+fn density_control(buttons: Res<Input<KeyCode>>, mut spawn_config: ResMut<SpawnConfig>) {
+    let mut density = spawn_config.density;
+    if buttons.just_pressed(KeyCode::LBracket) {
+        density -= 0.05;
+    }
+    if buttons.just_pressed(KeyCode::RBracket) {
+        density += 0.05;
+    }
+    if density != spawn_config.density {
+        spawn_config.density = density.clamp(0.0, 1.0);
+        info!("spawn density: {:.2}", spawn_config.density);
+    }
 }
 
-fn hex_dimensions(size: f32) -> HexDimensions {
-    HexDimensions {
-        width: size * 2.0,
-        height: 3f32.sqrt() * size,
+fn export_pattern(buttons: Res<Input<KeyCode>>, living: Res<LivingCells>) {
+    // Plain S pans the camera, so saving needs Ctrl held.
+    if !ctrl_held(&buttons) || !buttons.just_pressed(KeyCode::S) {
+        return;
+    }
+    let cells: Vec<AxialCoordinate> = living.0.iter().copied().collect();
+    match std::fs::write(PATTERN_FILE, pattern::encode(&cells)) {
+        Ok(()) => info!("saved {} cells to {}", cells.len(), PATTERN_FILE),
+        Err(e) => error!("failed to write {}: {}", PATTERN_FILE, e),
     }
 }
 
-#[derive(Copy, Clone, Component, PartialEq, Eq, Hash)]
-struct AxialCoordinate(IVec2);
-impl AxialCoordinate {
-    fn q(&self) -> i32 {
-        self.0.x
+fn import_pattern(
+    buttons: Res<Input<KeyCode>>,
+    wnds: Res<Windows>,
+    q_camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    orientation: Res<HexOrientation>,
+    hex_size: Res<HexSize>,
+    topology: Res<BoardTopology>,
+    mut living: ResMut<LivingCells>,
+    mut history: ResMut<HistoryBuffer>,
+    mut clipboard: ResMut<PatternClipboard>,
+) {
+    let load = buttons.just_pressed(KeyCode::L);
+    if !load && !buttons.just_pressed(KeyCode::V) {
+        return;
     }
-    fn r(&self) -> i32 {
-        self.0.y
+    let (camera, camera_transform) = q_camera.single();
+    let origin = match cursor_axial(&wnds, camera, camera_transform, *orientation, hex_size.0) {
+        Some(origin) => origin,
+        None => return,
+    };
+    if load {
+        // Shift+L loads a square-grid Life pattern instead of a hex one.
+        let cells = if buttons.pressed(KeyCode::LShift) || buttons.pressed(KeyCode::RShift) {
+            load_pattern(RLE_FILE, pattern::parse_rle)
+        } else {
+            load_pattern(PATTERN_FILE, pattern::decode)
+        };
+        match cells {
+            Some(cells) => clipboard.0 = cells,
+            None => return,
+        }
     }
-    fn s(&self) -> i32 {
-        -self.q() - self.r()
+    history.push(&living.0);
+    for coord in place_pattern(origin, &clipboard.0) {
+        // Cells that land outside the board are dropped.
+        if topology.contains(&coord) {
+            living.0.insert(coord);
+        }
     }
+}
 
-    fn new(q: i32, r: i32) -> Self {
-        Self(IVec2::new(q, r))
-    }
+/// `cells` moved so that their origin lands on `origin`, leaving out the cells whose
+/// coordinates, `s` included, would overflow there.
+fn place_pattern(
+    origin: AxialCoordinate,
+    cells: &[AxialCoordinate],
+) -> impl Iterator<Item = AxialCoordinate> + '_ {
+    cells.iter().filter_map(move |cell| {
+        let q = origin.q().checked_add(cell.q())?;
+        let r = origin.r().checked_add(cell.r())?;
+        q.checked_add(r)?.checked_neg()?;
+        Some(AxialCoordinate::new(q, r))
+    })
+}
 
-    fn neighbour_iter(&self) -> impl Iterator<Item = AxialCoordinate> + '_ {
-        let neighbour_possibilities = vec![
-            IVec2::new(1, 0),
-            IVec2::new(1, -1),
-            IVec2::new(0, -1),
-            IVec2::new(-1, 0),
-            IVec2::new(-1, 1),
-            IVec2::new(0, 1),
-        ];
-        neighbour_possibilities
-            .into_iter()
-            .map(|n| AxialCoordinate(self.0 + n))
+/// Reads the pattern at `path` and decodes it with `parse`, logging why if that fails.
+fn load_pattern<E: std::fmt::Display>(
+    path: &str,
+    parse: fn(&str) -> Result<Vec<AxialCoordinate>, E>,
+) -> Option<Vec<AxialCoordinate>> {
+    let input = match std::fs::read_to_string(path) {
+        Ok(input) => input,
+        Err(e) => {
+            error!("failed to read {}: {}", path, e);
+            return None;
+        }
+    };
+    match parse(&input) {
+        Ok(cells) => Some(cells),
+        Err(e) => {
+            error!("failed to load {}: {}", path, e);
+            None
+        }
     }
 }
 
-impl From<CubeCoordinate> for AxialCoordinate {
-    fn from(cc: CubeCoordinate) -> Self {
-        AxialCoordinate::new(cc.q(), cc.r())
-    }
+/// Rotates the clipboard pattern 60 degrees about its centroid, Q one way and E the other.
+fn rotate_clipboard(buttons: Res<Input<KeyCode>>, mut clipboard: ResMut<PatternClipboard>) {
+    let rotate: fn(&CubeCoordinate) -> CubeCoordinate = if buttons.just_pressed(KeyCode::Q) {
+        CubeCoordinate::rotate_left
+    } else if buttons.just_pressed(KeyCode::E) {
+        CubeCoordinate::rotate_right
+    } else {
+        return;
+    };
+    transform_about_centroid(&mut clipboard.0, rotate);
 }
 
-#[derive(Copy, Clone, Component, PartialEq, Eq, Hash)]
-struct CubeCoordinate(IVec3);
-impl CubeCoordinate {
-    fn q(&self) -> i32 {
-        self.0.x
+/// Mirrors the clipboard pattern about its centroid on F, across the q, r and s axes
+/// in turn.
+fn reflect_clipboard(
+    buttons: Res<Input<KeyCode>>,
+    mut clipboard: ResMut<PatternClipboard>,
+    mut axis: Local<usize>,
+) {
+    if !buttons.just_pressed(KeyCode::F) {
+        return;
     }
-    fn r(&self) -> i32 {
-        self.0.y
+    let reflections: [fn(&CubeCoordinate) -> CubeCoordinate; 3] = [
+        CubeCoordinate::reflect_q,
+        CubeCoordinate::reflect_r,
+        CubeCoordinate::reflect_s,
+    ];
+    transform_about_centroid(&mut clipboard.0, reflections[*axis]);
+    *axis = (*axis + 1) % reflections.len();
+}
+
+/// Applies `transform`, which works about the origin, to `cells` about their centroid
+/// instead so the pattern stays roughly in place.
+fn transform_about_centroid(
+    cells: &mut [AxialCoordinate],
+    transform: fn(&CubeCoordinate) -> CubeCoordinate,
+) {
+    if cells.is_empty() {
+        return;
     }
-    fn s(&self) -> i32 {
-        self.0.z
+    let sum = cells.iter().fold(Vec3::ZERO, |sum, cell| {
+        sum + CubeCoordinate::from(*cell).0.as_vec3()
+    });
+    let centroid = cube_round(sum / cells.len() as f32);
+    for cell in cells.iter_mut() {
+        let offset = CubeCoordinate(CubeCoordinate::from(*cell).0 - centroid.0);
+        *cell = CubeCoordinate(transform(&offset).0 + centroid.0).into();
     }
+}
 
-    fn new(q: i32, r: i32, s: i32) -> Self {
-        Self(IVec3::new(q, r, s))
+fn log_generation(mut events: EventReader<GenerationAdvanced>) {
+    for event in events.iter() {
+        debug!(
+            "generation {}: {} born, {} died",
+            event.generation,
+            event.births.len(),
+            event.deaths.len()
+        );
     }
 }
 
-fn axial_to_screen(size: f32, hex_pos: &AxialCoordinate) -> Vec2 {
-    const FLAT_BASIS_C: [f32; 4] = [3. / 2., SQRT_3 / 2., 0., SQRT_3];
-    let flat_basis: Mat2 = Mat2::from_cols_array(&FLAT_BASIS_C);
-    size * flat_basis * hex_pos.0.as_vec2()
+/// Randomizes the cells of `topology` within `HEADLESS_RADIUS` of its origin from
+/// `seed` and prints the population after each of `generations` ticks, without
+/// opening a window.
+fn run_headless(generations: u64, seed: u64, topology: &BoardTopology) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let density = SpawnConfig::default().density;
+    let rules = LifeRules::default();
+    let mut living = random_cells(
+        &mut rng,
+        topology
+            .origin
+            .spiral(HEADLESS_RADIUS)
+            .filter(|coord| topology.contains(coord)),
+        density,
+    );
+    println!("generation 0: {}", living.len());
+    for generation in 1..=generations {
+        living = next_generation_with(&living, &rules, Neighbourhood::default(), |coord| {
+            topology.resolve(coord)
+        });
+        living.retain(|cell| topology.contains(cell));
+        println!("generation {}: {}", generation, living.len());
+    }
 }
 
-fn screen_to_cube_float(size: f32, point: Vec2) -> Vec3 {
-    const FLAT_BASIS_C: [f32; 4] = [2. / 3., -1. / 3., 0., SQRT_3 / 3.];
-    let flat_basis: Mat2 = Mat2::from_cols_array(&FLAT_BASIS_C);
-    let axial_float = flat_basis * point / size;
-    Vec3::from((axial_float, -axial_float.x - axial_float.y))
+/// Everything that decides what a tick does to the board.
+#[derive(SystemParam)]
+struct SimulationRules<'w, 's> {
+    rules: Res<'w, SpeciesRules>,
+    neighbourhood: Res<'w, Neighbourhood>,
+    topology: Res<'w, BoardTopology>,
+    bounds: Res<'w, SimBounds>,
+    locked: Res<'w, LockedCells>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
 }
 
-fn cube_round(cube: Vec3) -> CubeCoordinate {
-    let rounded_cube = cube.round();
-    let diff = (rounded_cube - cube).abs();
-
-    let mut ret_cube: IVec3 = rounded_cube.as_ivec3();
-    if diff.x > diff.y && diff.x > diff.z {
-        ret_cube.x = -ret_cube.y - ret_cube.z;
-    } else if diff.y > diff.z {
-        ret_cube.y = -ret_cube.x - ret_cube.z;
-    } else {
-        ret_cube.z = -ret_cube.x - ret_cube.y;
+impl SimulationRules<'_, '_> {
+    /// Whether ticks leave `cell` the way it is, for being outside the bounds or locked.
+    fn frozen(&self, cell: &AxialCoordinate) -> bool {
+        self.bounds.0.is_some_and(|bounds| !bounds.contains(cell)) || self.locked.0.contains(cell)
     }
+}
 
-    CubeCoordinate(ret_cube)
+fn game_of_life(
+    time: Res<Time>,
+    mut game_state: ResMut<GameState>,
+    mut timer: ResMut<SimTimer>,
+    mut generation: ResMut<Generation>,
+    sim: SimulationRules,
+    mut living: ResMut<LivingCells>,
+    mut ages: ResMut<CellAges>,
+    mut species: ResMut<CellSpecies>,
+    max_population: Res<MaxPopulation>,
+    limit: Res<GenerationLimit>,
+    mut advanced: EventWriter<GenerationAdvanced>,
+    mut stable: ResMut<Stable>,
+    mut state_history: ResMut<StateHistory>,
+    mut oscillator: ResMut<OscillatorPeriod>,
+    mut sim_history: ResMut<SimHistory>,
+    mut falling_behind: Local<bool>,
+) {
+    // Time left over from before a pause keeps until the simulation runs again, even if
+    // the period is shortened below it in the meantime.
+    let due = if game_state.started {
+        timer.accumulator += time.delta_seconds();
+        (timer.accumulator / timer.period) as u32
+    } else {
+        0
+    };
+    let timer_driven = due > 0;
+    let ticks = if timer_driven {
+        timer.accumulator -= due as f32 * timer.period;
+        let ticks = due.min(timer.max_steps_per_frame);
+        if due > ticks && !*falling_behind {
+            warn!(
+                "simulation can't keep up; dropping {} of {} generations due this frame",
+                due - ticks,
+                due
+            );
+        }
+        *falling_behind = due > ticks;
+        ticks
+    } else if game_state.steps_requested > 0 {
+        // Steps requested on a frame the timer fires wait for the next one.
+        std::mem::take(&mut game_state.steps_requested)
+    } else {
+        return;
+    };
+    // All ticks of a frame are simulated back to back; the board is only redrawn
+    // after the last one.
+    for _ in 0..ticks {
+        let (mut next, mut next_species) = next_generation_species(
+            &living.0,
+            &species.0,
+            &sim.rules.0,
+            *sim.neighbourhood,
+            |coord| sim.topology.resolve(coord),
+        );
+        // Hexes off the board are never alive, so the edge cells simply have fewer
+        // hexes that can count towards their living neighbours. Counting the hexes
+        // beyond the edge as dead or leaving them out is the same thing under rules
+        // that only count living neighbours.
+        next.retain(|cell| sim.topology.contains(cell));
+        apply_max_age(&mut next, &living.0, &ages.0, &species.0, &sim.rules.0);
+        // Put the cells outside the bounds and the locked ones back the way they were.
+        let frozen = |cell: &AxialCoordinate| sim.frozen(cell);
+        if sim.bounds.0.is_some() || !sim.locked.0.is_empty() {
+            next.retain(|cell| !frozen(cell));
+            next_species.retain(|cell, _| !frozen(cell));
+            for cell in living.0.iter().filter(|cell| frozen(cell)) {
+                next.insert(*cell);
+                if let Some(kind) = species.0.get(cell) {
+                    next_species.insert(*cell, *kind);
+                }
+            }
+        }
+        if next.len() > max_population.0 {
+            warn!(
+                "generation {} would have {} cells, more than the limit of {}; pausing",
+                generation.0 + 1,
+                next.len(),
+                max_population.0
+            );
+            game_state.started = false;
+            break;
+        }
+        if sim_history.capacity > 0 {
+            if sim_history.snapshots.len() >= sim_history.capacity {
+                sim_history.snapshots.pop_front();
+            }
+            sim_history.snapshots.push_back(SimSnapshot {
+                generation: generation.0,
+                living: living.0.clone(),
+                ages: ages.0.clone(),
+                species: species.0.clone(),
+            });
+        }
+        species.0 = next_species;
+        let is_stable = next == living.0;
+        ages.0 = next_ages(&ages.0, &living.0, &next);
+        let births = next.difference(&living.0).copied().collect();
+        let deaths = living.0.difference(&next).copied().collect();
+        living.0 = next;
+        generation.0 += 1;
+        advanced.send(GenerationAdvanced {
+            generation: generation.0,
+            births,
+            deaths,
+        });
+        if limit.0 == Some(generation.0) && game_state.started {
+            info!("reached the generation limit of {}", generation.0);
+            game_state.started = false;
+        }
+        if is_stable && !stable.0 {
+            info!("stabilized at generation {}", generation.0);
+            if game_state.pause_on_stable {
+                game_state.started = false;
+            }
+        }
+        stable.0 = is_stable;
+
+        let hash = state_hash(&living.0);
+        let period = state_history
+            .hashes
+            .iter()
+            .rev()
+            .position(|previous| *previous == hash)
+            .map(|i| i + 1)
+            .filter(|period| *period > 1);
+        if let Some(period) = period.filter(|period| oscillator.0 != Some(*period)) {
+            info!("oscillator of period {} detected", period);
+        }
+        oscillator.0 = period;
+        if state_history.hashes.len() >= state_history.capacity {
+            state_history.hashes.pop_front();
+        }
+        state_history.hashes.push_back(hash);
+        // Reaching the limit or a stable board pauses the rest of a timer-driven batch,
+        // while explicitly requested steps all run.
+        if timer_driven && !game_state.started {
+            break;
+        }
+    }
+}
+
+/// Brings the `Alive`, `Age` and `Species` components on spawned hexes in line with
+/// `LivingCells`, `CellAges` and `CellSpecies`.
+fn sync_alive(
+    living: Res<LivingCells>,
+    mut ages: ResMut<CellAges>,
+    mut species: ResMut<CellSpecies>,
+    query: Query<(Entity, &AxialCoordinate, Option<&Alive>)>,
+    mut commands: Commands,
+) {
+    if !living.is_changed() && !ages.is_changed() && !species.is_changed() {
+        return;
+    }
+    // Cells killed by an edit would otherwise keep their age if painted back in.
+    if ages.0.keys().any(|cell| !living.0.contains(cell)) {
+        ages.0.retain(|cell, _| living.0.contains(cell));
+    }
+    if species.0.keys().any(|cell| !living.0.contains(cell)) {
+        species.0.retain(|cell, _| living.0.contains(cell));
+    }
+    for (entity, coord, alive) in query.iter() {
+        if living.0.contains(coord) {
+            let age = ages.0.get(coord).copied().unwrap_or(0);
+            let kind = species.0.get(coord).copied().unwrap_or(0);
+            commands
+                .entity(entity)
+                .insert_bundle((Alive, Age(age), Species(kind)));
+        } else if alive.is_some() {
+            commands
+                .entity(entity)
+                .remove_bundle::<(Alive, Age, Species)>();
+        }
+    }
+}
+
+fn highlight_hex(
+    keys: Res<Input<KeyCode>>,
+    topology: Res<BoardTopology>,
+    selected: Res<SelectedSpecies>,
+    brush: Res<BrushSize>,
+    locked: Res<LockedCells>,
+    mut clicks: EventReader<HexClicked>,
+    mut living: ResMut<LivingCells>,
+    mut species: ResMut<CellSpecies>,
+) {
+    for click in clicks.iter() {
+        // Alt+click flood selects instead.
+        if click.button != MouseButton::Left || alt_held(&keys) {
+            continue;
+        }
+        let axial = click.coord;
+        if keys.pressed(KeyCode::LShift) || keys.pressed(KeyCode::RShift) {
+            for coord in axial.within_range(SELECT_RADIUS) {
+                if topology.contains(&coord) {
+                    set_alive(&mut living, &mut species, &locked, coord, true, selected.0);
+                }
+            }
+        } else if topology.contains(&axial) {
+            // The whole brush follows the hex under the cursor.
+            let alive = !living.0.contains(&axial);
+            for coord in axial.within_range(brush.0) {
+                if topology.contains(&coord) {
+                    set_alive(&mut living, &mut species, &locked, coord, alive, selected.0);
+                }
+            }
+        }
+    }
+}
+
+fn pick_hex(
+    buttons: Res<Input<MouseButton>>,
+    picker: HexPicker,
+    mut clicks: EventWriter<HexClicked>,
+) {
+    // The minimap takes clicks for moving the camera.
+    if buttons.get_just_pressed().next().is_none() || minimap::contains_cursor(&picker.wnds) {
+        return;
+    }
+    // The clicked hex is picked as `PickingConfig` says.
+    let (world_pos, coord, entity) = match picker.cursor_hex() {
+        Some((world_pos, coord, Some(entity))) => (world_pos, coord, entity),
+        _ => return,
+    };
+    for button in buttons.get_just_pressed() {
+        clicks.send(HexClicked {
+            entity,
+            coord,
+            button: *button,
+            world_pos,
+        });
+    }
+}
+
+/// Toggles keyboard editing on K and, while it is on, moves `CursorHex` one hex with
+/// the numpad and toggles it with Return or Space.
+fn keyboard_editing(
+    keys: Res<Input<KeyCode>>,
+    orientation: Res<HexOrientation>,
+    topology: Res<BoardTopology>,
+    selected: Res<SelectedSpecies>,
+    locked: Res<LockedCells>,
+    mut editing: ResMut<KeyboardEditing>,
+    mut cursor: ResMut<CursorHex>,
+    mut living: ResMut<LivingCells>,
+    mut species: ResMut<CellSpecies>,
+    mut history: ResMut<HistoryBuffer>,
+) {
+    if keys.just_pressed(KeyCode::K) {
+        editing.0 = !editing.0;
+        info!("keyboard editing {}", if editing.0 { "on" } else { "off" });
+    }
+    if !editing.0 {
+        return;
+    }
+    // Numpad keys laid out like the hex's six neighbours on screen.
+    let moves = match *orientation {
+        HexOrientation::FlatTop => [
+            (KeyCode::Numpad8, IVec2::new(0, 1)),
+            (KeyCode::Numpad9, IVec2::new(1, 0)),
+            (KeyCode::Numpad3, IVec2::new(1, -1)),
+            (KeyCode::Numpad2, IVec2::new(0, -1)),
+            (KeyCode::Numpad1, IVec2::new(-1, 0)),
+            (KeyCode::Numpad7, IVec2::new(-1, 1)),
+        ],
+        HexOrientation::PointyTop => [
+            (KeyCode::Numpad6, IVec2::new(1, 0)),
+            (KeyCode::Numpad9, IVec2::new(0, 1)),
+            (KeyCode::Numpad7, IVec2::new(-1, 1)),
+            (KeyCode::Numpad4, IVec2::new(-1, 0)),
+            (KeyCode::Numpad1, IVec2::new(0, -1)),
+            (KeyCode::Numpad3, IVec2::new(1, -1)),
+        ],
+    };
+    for (key, step) in moves {
+        if keys.just_pressed(key) {
+            let next = topology.resolve(AxialCoordinate(cursor.0 .0 + step));
+            if topology.contains(&next) {
+                cursor.0 = next;
+            }
+        }
+    }
+    // Locked hexes can't be toggled, so leave the history alone for them.
+    if keys.any_just_pressed([KeyCode::Return, KeyCode::Space])
+        && topology.contains(&cursor.0)
+        && !locked.0.contains(&cursor.0)
+    {
+        history.push(&living.0);
+        let alive = !living.0.contains(&cursor.0);
+        set_alive(
+            &mut living,
+            &mut species,
+            &locked,
+            cursor.0,
+            alive,
+            selected.0,
+        );
+    }
+}
+
+/// Outlines `CursorHex` while editing from the keyboard.
+fn draw_cursor_marker(
+    cursor: Res<CursorHex>,
+    editing: Res<KeyboardEditing>,
+    orientation: Res<HexOrientation>,
+    hex_size: Res<HexSize>,
+    mut query: Query<(&mut Transform, &mut Visibility), With<CursorMarker>>,
+) {
+    for (mut transform, mut visibility) in query.iter_mut() {
+        visibility.is_visible = editing.0;
+        transform.translation = axial_to_screen(*orientation, hex_size.0, &cursor.0).extend(1.);
+        transform.scale = Vec3::splat(hex_size.0 / HEX_SIZE);
+    }
+}
+
+/// Scrolls the view after `CursorHex` when it moves too close to the window edge.
+fn follow_cursor_hex(
+    cursor: Res<CursorHex>,
+    editing: Res<KeyboardEditing>,
+    orientation: Res<HexOrientation>,
+    hex_size: Res<HexSize>,
+    mut q_camera: Query<(&OrthographicProjection, &mut Transform), With<MainCamera>>,
+) {
+    if !editing.0 || !(cursor.is_changed() || editing.is_changed()) {
+        return;
+    }
+    let pos = axial_to_screen(*orientation, hex_size.0, &cursor.0);
+    for (projection, mut transform) in q_camera.iter_mut() {
+        let half_view = Vec2::new(projection.right, projection.top) * projection.scale;
+        let reach = (half_view - Vec2::splat(CURSOR_MARGIN * 2. * hex_size.0)).max(Vec2::ZERO);
+        let center = transform.translation.truncate();
+        let center = center.clamp(pos - reach, pos + reach);
+        transform.translation = center.extend(transform.translation.z);
+    }
+}
+
+/// Makes `coord` a living cell of species `kind`, or a dead one, unless it is locked.
+fn set_alive(
+    living: &mut LivingCells,
+    species: &mut CellSpecies,
+    locked: &LockedCells,
+    coord: AxialCoordinate,
+    alive: bool,
+    kind: u8,
+) {
+    if locked.0.contains(&coord) {
+        return;
+    }
+    if alive {
+        living.0.insert(coord);
+        species.0.insert(coord, kind);
+    } else {
+        living.0.remove(&coord);
+    }
+}
+
+/// The paint stroke in progress, if any.
+#[derive(Default)]
+struct PaintStroke {
+    /// The button of the `HexClicked` that started the stroke, while it is held.
+    button: Option<MouseButton>,
+    /// The hex painted last, so that dragging within it paints it only once.
+    last_painted: Option<AxialCoordinate>,
+    /// Whether the board from before the stroke is in the history yet.
+    saved: bool,
+}
+
+/// Starts a stroke on a left or right click on a hex, then sets hexes alive while
+/// dragging with the left button held and dead with the right.
+fn paint_hex(
+    buttons: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    picker: HexPicker,
+    topology: Res<BoardTopology>,
+    selected: Res<SelectedSpecies>,
+    brush: Res<BrushSize>,
+    locked: Res<LockedCells>,
+    mut living: ResMut<LivingCells>,
+    mut species: ResMut<CellSpecies>,
+    mut history: ResMut<HistoryBuffer>,
+    mut clicks: EventReader<HexClicked>,
+    mut stroke: Local<PaintStroke>,
+) {
+    let click = clicks
+        .iter()
+        .find(|click| matches!(click.button, MouseButton::Left | MouseButton::Right));
+    let mut started = None;
+    // Alt+click flood selects instead.
+    if let Some(click) = click.filter(|_| !alt_held(&keys)) {
+        *stroke = PaintStroke {
+            button: Some(click.button),
+            ..Default::default()
+        };
+        started = Some(click.coord);
+    }
+    let alive = match stroke.button {
+        Some(button) if buttons.pressed(button) => button == MouseButton::Left,
+        _ => {
+            stroke.button = None;
+            return;
+        }
+    };
+    // The minimap takes clicks for moving the camera.
+    if alt_held(&keys) || minimap::contains_cursor(&picker.wnds) {
+        stroke.last_painted = None;
+        return;
+    }
+    let axial = match started.or_else(|| picker.cursor_hex().map(|(_, axial, _)| axial)) {
+        Some(axial) => axial,
+        None => return,
+    };
+    // Only paint once per hex so dragging back and forth over it doesn't flicker.
+    if stroke.last_painted == Some(axial) {
+        return;
+    }
+    stroke.last_painted = Some(axial);
+    // The hexes under a fresh left click are toggled by `highlight_hex` instead, after
+    // the snapshot here covering the whole stroke, so only work out what it will edit.
+    let toggling = started.is_some() && alive;
+    let shift = keys.pressed(KeyCode::LShift) || keys.pressed(KeyCode::RShift);
+    let (radius, alive) = if !toggling {
+        (brush.0, alive)
+    } else if shift {
+        (SELECT_RADIUS, true)
+    } else if topology.contains(&axial) {
+        (brush.0, !living.0.contains(&axial))
+    } else {
+        return;
+    };
+    let edits: Vec<AxialCoordinate> = axial
+        .within_range(radius)
+        .filter(|coord| topology.contains(coord) && !locked.0.contains(coord))
+        .filter(|coord| {
+            let kind = species.0.get(coord).copied().unwrap_or(0);
+            living.0.contains(coord) != alive || (alive && kind != selected.0)
+        })
+        .collect();
+    if edits.is_empty() {
+        return;
+    }
+    // One snapshot per stroke, taken before its first edit.
+    if !stroke.saved {
+        history.push(&living.0);
+        stroke.saved = true;
+    }
+    if toggling {
+        return;
+    }
+    for coord in edits {
+        set_alive(&mut living, &mut species, &locked, coord, alive, selected.0);
+    }
+}
+
+/// Selects the region of the hex under an alt+click, see `FloodSelection`.
+fn flood_select(
+    keys: Res<Input<KeyCode>>,
+    topology: Res<BoardTopology>,
+    hex_map: Res<HexMap>,
+    living: Res<LivingCells>,
+    mut clicks: EventReader<HexClicked>,
+    mut selection: ResMut<FloodSelection>,
+) {
+    let start = match clicks
+        .iter()
+        .find(|click| click.button == MouseButton::Left && alt_held(&keys))
+    {
+        Some(click) => click.coord,
+        None => return,
+    };
+    let alive = living.0.contains(&start);
+    let mut visited = HashSet::default();
+    visited.insert(start);
+    let mut frontier = vec![start];
+    // Only spawned hexes are taken in, so the fill stops at the edge of the view even
+    // on an unbounded board.
+    'fill: while let Some(coord) = frontier.pop() {
+        for neighbour in coord.neighbour_iter() {
+            let neighbour = topology.resolve(neighbour);
+            if !hex_map.0.contains_key(&neighbour)
+                || living.0.contains(&neighbour) != alive
+                || !visited.insert(neighbour)
+            {
+                continue;
+            }
+            if visited.len() >= MAX_FLOOD_SELECT {
+                warn!("flood select stopped at {} hexes", MAX_FLOOD_SELECT);
+                break 'fill;
+            }
+            frontier.push(neighbour);
+        }
+    }
+    selection.0 = visited;
+}
+
+/// Kills the flood selected hexes on Delete or fills them with the selected species
+/// on Insert. Escape drops the selection.
+fn edit_selection(
+    keys: Res<Input<KeyCode>>,
+    selected: Res<SelectedSpecies>,
+    locked: Res<LockedCells>,
+    mut selection: ResMut<FloodSelection>,
+    mut living: ResMut<LivingCells>,
+    mut species: ResMut<CellSpecies>,
+    mut history: ResMut<HistoryBuffer>,
+) {
+    if keys.just_pressed(KeyCode::Escape) && !selection.0.is_empty() {
+        selection.0.clear();
+        return;
+    }
+    let alive = if keys.just_pressed(KeyCode::Insert) {
+        true
+    } else if keys.just_pressed(KeyCode::Delete) {
+        false
+    } else {
+        return;
+    };
+    // Nothing to edit, or to undo, when no selected hex is unlocked.
+    if selection.0.iter().all(|coord| locked.0.contains(coord)) {
+        return;
+    }
+    history.push(&living.0);
+    for coord in selection.0.iter() {
+        set_alive(
+            &mut living,
+            &mut species,
+            &locked,
+            *coord,
+            alive,
+            selected.0,
+        );
+    }
+}
+
+/// Locks or unlocks the hovered hex on X, see `LockedCells`.
+fn lock_hex(
+    keys: Res<Input<KeyCode>>,
+    hovered: Res<HoveredHex>,
+    q_hex: Query<&AxialCoordinate>,
+    mut locked: ResMut<LockedCells>,
+) {
+    if !keys.just_pressed(KeyCode::X) {
+        return;
+    }
+    if let Some(coord) = hovered.0.and_then(|entity| q_hex.get(entity).ok()) {
+        if !locked.0.remove(coord) {
+            locked.0.insert(*coord);
+        }
+    }
+}
+
+/// The axial coordinate of the hex under the mouse cursor, if the cursor is in the window.
+fn cursor_axial(
+    wnds: &Windows,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    orientation: HexOrientation,
+    size: f32,
+) -> Option<AxialCoordinate> {
+    let world_pos = cursor_to_world(wnds, camera, camera_transform)?;
+    Some(screen_to_axial(orientation, size, world_pos))
+}
+
+/// The hex containing `world_pos` and its entity, which is `None` while that hex isn't
+/// spawned. With `picking.ignore_gaps` set, points between hexes, outside the hexagon
+/// of inner radius `inset * size`, pick no hex at all.
+fn hex_at_world(
+    hex_map: &HexMap,
+    orientation: HexOrientation,
+    size: f32,
+    picking: &PickingConfig,
+    inset: f32,
+    world_pos: Vec2,
+) -> Option<(AxialCoordinate, Option<Entity>)> {
+    let axial = screen_to_axial(orientation, size, world_pos);
+    if picking.ignore_gaps {
+        let local = world_pos - axial_to_screen(orientation, size, &axial);
+        if !in_hexagon(orientation, local, inset * size) {
+            return None;
+        }
+    }
+    Some((axial, hex_map.0.get(&axial).copied()))
+}
+
+/// Everything needed to pick the hex under the mouse cursor.
+#[derive(SystemParam)]
+struct HexPicker<'w, 's> {
+    wnds: Res<'w, Windows>,
+    q_camera: Query<'w, 's, (&'static Camera, &'static GlobalTransform), With<MainCamera>>,
+    orientation: Res<'w, HexOrientation>,
+    hex_size: Res<'w, HexSize>,
+    picking: Res<'w, PickingConfig>,
+    inset: Res<'w, HexInset>,
+    hex_map: Res<'w, HexMap>,
+}
+
+impl HexPicker<'_, '_> {
+    /// The world position of the cursor and the hex under it, see `hex_at_world`.
+    fn cursor_hex(&self) -> Option<(Vec2, AxialCoordinate, Option<Entity>)> {
+        let (camera, camera_transform) = self.q_camera.single();
+        let world_pos = cursor_to_world(&self.wnds, camera, camera_transform)?;
+        let (axial, entity) = hex_at_world(
+            &self.hex_map,
+            *self.orientation,
+            self.hex_size.0,
+            &self.picking,
+            self.inset.0,
+            world_pos,
+        )?;
+        Some((world_pos, axial, entity))
+    }
+}
+
+/// The world-space position of the mouse cursor, or `None` when it is outside the window.
+fn cursor_to_world(
+    wnds: &Windows,
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+) -> Option<Vec2> {
+    let wnd = wnds.get(camera.window)?;
+    let screen_pos = wnd.cursor_position()?;
+    let window_size = Vec2::new(wnd.width(), wnd.height());
+    // convert screen position [0..resolution] to ndc [-1..1] (gpu coordinates)
+    let ndc = (screen_pos / window_size) * 2.0 - Vec2::ONE;
+    // matrix for undoing the projection and camera transform
+    let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix.inverse();
+    // use it to convert ndc to world-space coordinates
+    let world_pos = ndc_to_world.project_point3(ndc.extend(-1.0));
+    // reduce it to a 2D value
+    Some(world_pos.truncate())
+}
+
+fn track_hover(picker: HexPicker, mut hovered: ResMut<HoveredHex>) {
+    let entity = picker.cursor_hex().and_then(|(_, _, entity)| entity);
+    if hovered.0 != entity {
+        hovered.0 = entity;
+    }
+}
+
+/// Recolors only the hexes whose age, liveness, trail or hover state changed this
+/// frame, or every hex when the theme, color mode, selection or locks changed. Frames
+/// without a tick, an edit, a fading trail or a hover change touch no hex at all, so
+/// this needs no run criterion of its own.
+fn color_hex(
+    theme: Res<Theme>,
+    hovered: Res<HoveredHex>,
+    selection: Res<FloodSelection>,
+    color_mode: Res<ColorMode>,
+    living: Res<LivingCells>,
+    hex_map: Res<HexMap>,
+    neighbourhood: Res<Neighbourhood>,
+    topology: Res<BoardTopology>,
+    locked: Res<LockedCells>,
+    mut last_hovered: Local<Option<Entity>>,
+    spawned: Query<Entity, Added<AxialCoordinate>>,
+    aged: Query<Entity, Changed<Age>>,
+    died: RemovedComponents<Alive>,
+    trailed: Query<Entity, Changed<Trail>>,
+    trail_ended: RemovedComponents<Trail>,
+    mut query: Query<(Entity, &mut SmudShape, HexLook), Without<ColorTween>>,
+) {
+    let highlighted =
+        |entity, coord: &AxialCoordinate| hovered.0 == Some(entity) || selection.0.contains(coord);
+    let tint = |coord: &AxialCoordinate, color| with_lock(color, locked.0.contains(coord));
+    let color =
+        |entity, coord: &AxialCoordinate, cell: Option<LivingCell<'_>>, trail: Option<&Trail>| {
+            let highlighted = highlighted(entity, coord);
+            let color = match (cell, trail) {
+                (None, Some(trail)) => trail_color(&theme, trail.0, highlighted),
+                _ => hex_color(&theme, cell, highlighted),
+            };
+            tint(coord, color)
+        };
+    if *color_mode == ColorMode::NeighbourCount {
+        // Any birth or death changes the counts around it, so redraw everything.
+        let changed = theme.is_changed()
+            || selection.is_changed()
+            || locked.is_changed()
+            || color_mode.is_changed()
+            || living.is_changed()
+            || hex_map.is_changed()
+            || hovered.0 != *last_hovered;
+        if changed {
+            for (entity, mut hex, (coord, _, _)) in query.iter_mut() {
+                let count = coord
+                    .neighbours(*neighbourhood)
+                    .filter(|neighbour| living.0.contains(&topology.resolve(*neighbour)))
+                    .count();
+                let alive = living.0.contains(coord);
+                let color = neighbour_count_color(&theme, count, alive, highlighted(entity, coord));
+                hex.color = tint(coord, color);
+            }
+            *last_hovered = hovered.0;
+        }
+        return;
+    }
+    if theme.is_changed()
+        || selection.is_changed()
+        || locked.is_changed()
+        || color_mode.is_changed()
+    {
+        for (entity, mut hex, (coord, cell, trail)) in query.iter_mut() {
+            hex.color = color(entity, coord, cell, trail);
+        }
+        *last_hovered = hovered.0;
+        return;
+    }
+    // Hexes streamed in as the camera moves start out black.
+    let mut dirty: Vec<Entity> = spawned
+        .iter()
+        .chain(aged.iter())
+        .chain(died.iter())
+        .chain(trailed.iter())
+        .chain(trail_ended.iter())
+        .collect();
+    if hovered.0 != *last_hovered {
+        dirty.extend(last_hovered.iter().chain(hovered.0.iter()));
+        *last_hovered = hovered.0;
+    }
+    for entity in dirty {
+        // Hexes that died this frame may also have been despawned.
+        if let Ok((_, mut hex, (coord, cell, trail))) = query.get_mut(entity) {
+            hex.color = color(entity, coord, cell, trail);
+        }
+    }
+}
+
+/// Fades the hexes of cells born by the last tick towards their new color and leaves a
+/// trail on the hexes of cells it killed.
+fn start_color_tweens(
+    mut events: EventReader<GenerationAdvanced>,
+    mut commands: Commands,
+    theme: Res<Theme>,
+    color_mode: Res<ColorMode>,
+    species: Res<CellSpecies>,
+    hex_map: Res<HexMap>,
+    query: Query<&SmudShape>,
+) {
+    // The neighbour counts change too widely for per-cell fades to make sense.
+    if *color_mode != ColorMode::AliveDead {
+        return;
+    }
+    for event in events.iter() {
+        for coord in event.births.iter() {
+            let entity = match hex_map.0.get(coord) {
+                Some(entity) => *entity,
+                None => continue,
+            };
+            let kind = species.0.get(coord).copied().unwrap_or(0);
+            if let Ok(shape) = query.get(entity) {
+                commands.entity(entity).insert(ColorTween {
+                    from: shape.color,
+                    to: hex_color(&theme, Some((&Age(0), &Species(kind))), false),
+                    elapsed: 0.,
+                });
+            }
+        }
+        // The trail fades the dead hexes out on its own.
+        for coord in event.deaths.iter() {
+            if let Some(entity) = hex_map.0.get(coord) {
+                commands.entity(*entity).insert(Trail(1.));
+            }
+        }
+    }
+}
+
+/// Fades trails and drops them once they are all but gone.
+fn decay_trails(mut commands: Commands, time: Res<Time>, mut query: Query<(Entity, &mut Trail)>) {
+    for (entity, mut trail) in query.iter_mut() {
+        trail.0 -= time.delta_seconds() / TRAIL_SECONDS;
+        if trail.0 < TRAIL_CUTOFF {
+            commands.entity(entity).remove::<Trail>();
+        }
+    }
+}
+
+fn advance_color_tweens(
+    mut commands: Commands,
+    time: Res<Time>,
+    theme: Res<Theme>,
+    hovered: Res<HoveredHex>,
+    selection: Res<FloodSelection>,
+    locked: Res<LockedCells>,
+    mut query: Query<(
+        Entity,
+        &AxialCoordinate,
+        &mut SmudShape,
+        &mut ColorTween,
+        Option<LivingCell>,
+    )>,
+) {
+    for (entity, coord, mut shape, mut tween, cell) in query.iter_mut() {
+        tween.elapsed += time.delta_seconds();
+        if tween.elapsed >= FADE_SECONDS {
+            // Finish on the color `color_hex` would pick, in case the cell aged meanwhile.
+            let highlighted = hovered.0 == Some(entity) || selection.0.contains(coord);
+            let color = hex_color(&theme, cell, highlighted);
+            shape.color = with_lock(color, locked.0.contains(coord));
+            commands.entity(entity).remove::<ColorTween>();
+            continue;
+        }
+        let t = tween.elapsed / FADE_SECONDS;
+        let from = Vec4::from(tween.from.as_rgba_f32());
+        let to = Vec4::from(tween.to.as_rgba_f32());
+        shape.color = from.lerp(to, t).to_array().into();
+    }
+}
+
+/// Living cells start out in their species' color and darken as they age; dead
+/// cells stay the dead color. Hovering or selecting adds the hover color to either.
+fn hex_color(theme: &Theme, cell: Option<LivingCell>, hovered: bool) -> Color {
+    let [r, g, b, _] = match cell {
+        Some((Age(age), Species(kind))) => {
+            let t = (*age).min(AGE_GRADIENT_GENERATIONS) as f32 / AGE_GRADIENT_GENERATIONS as f32;
+            let color = match kind {
+                0 => theme.alive,
+                kind => SPECIES_COLORS[(*kind as usize - 1).min(SPECIES_COLORS.len() - 1)],
+            };
+            let [r, g, b, a] = color.as_rgba_f32();
+            let fade = 1. - 0.55 * t;
+            [r * fade, g * fade, b * fade, a]
+        }
+        None => theme.dead.as_rgba_f32(),
+    };
+    with_hover(theme, [r, g, b], hovered)
+}
+
+/// The dead color blended towards the alive color by `trail`, with the hover color
+/// added if `hovered` is set.
+fn trail_color(theme: &Theme, trail: f32, hovered: bool) -> Color {
+    let dead = Vec4::from(theme.dead.as_rgba_f32());
+    let alive = Vec4::from(theme.alive.as_rgba_f32());
+    let [r, g, b, _] = dead.lerp(alive, trail.clamp(0., 1.)).to_array();
+    with_hover(theme, [r, g, b], hovered)
+}
+
+/// The color of a hex with `count` living neighbours in `ColorMode::NeighbourCount`.
+fn neighbour_count_color(theme: &Theme, count: usize, alive: bool, hovered: bool) -> Color {
+    let [r, g, b, _] = NEIGHBOUR_COLORS[count.min(NEIGHBOUR_COLORS.len() - 1)].as_rgba_f32();
+    let shade = if alive { 1. } else { 0.4 };
+    with_hover(theme, [r * shade, g * shade, b * shade], hovered)
+}
+
+/// `rgb` with the theme's hover color added if `hovered` is set.
+fn with_hover(theme: &Theme, [r, g, b]: [f32; 3], hovered: bool) -> Color {
+    if hovered {
+        let [hr, hg, hb, _] = theme.hover.as_rgba_f32();
+        Color::rgb(r + hr, g + hg, b + hb)
+    } else {
+        Color::rgb(r, g, b)
+    }
+}
+
+/// `color` blended halfway towards `LOCKED_TINT` if `locked` is set.
+fn with_lock(color: Color, locked: bool) -> Color {
+    if locked {
+        let color = Vec4::from(color.as_rgba_f32());
+        let tint = Vec4::from(LOCKED_TINT.as_rgba_f32());
+        color.lerp(tint, 0.5).to_array().into()
+    } else {
+        color
+    }
+}
+
+/// Moves the camera to frame all living cells on Home, or back to the board's origin at the
+/// default zoom on Shift+Home or when there are none.
+fn center_camera(
+    kb: Res<Input<KeyCode>>,
+    wnds: Res<Windows>,
+    orientation: Res<HexOrientation>,
+    hex_size: Res<HexSize>,
+    limits: Res<CameraLimits>,
+    living: Res<LivingCells>,
+    topology: Res<BoardTopology>,
+    tour: Res<CameraTour>,
+    mut query: Query<(&mut OrthographicProjection, &mut Transform), With<MainCamera>>,
+) {
+    if !kb.just_pressed(KeyCode::Home) || tour.enabled {
+        return;
+    }
+    let (mut projection, mut transform) = query.single_mut();
+    let reset = kb.pressed(KeyCode::LShift) || kb.pressed(KeyCode::RShift);
+    let (min, max) = match living_bounds(*orientation, hex_size.0, &living) {
+        Some(bounds) if !reset => bounds,
+        _ => {
+            let origin = axial_to_screen(*orientation, hex_size.0, &topology.origin);
+            transform.translation = origin.extend(transform.translation.z);
+            projection.scale = DEFAULT_ZOOM;
+            return;
+        }
+    };
+    let center = (min + max) / 2.;
+    transform.translation = Vec3::new(center.x, center.y, transform.translation.z);
+    if let Some(wnd) = wnds.get_primary() {
+        // Leave a margin of a few hexes around the pattern.
+        let extent = max - min + Vec2::splat(8. * hex_size.0);
+        let window = Vec2::new(wnd.width(), wnd.height());
+        projection.scale = (extent / window)
+            .max_element()
+            .clamp(limits.min_zoom, limits.max_zoom);
+    }
+}
+
+/// The world-space corners of the box around the centers of all living cells, or
+/// `None` when there are none.
+fn living_bounds(
+    orientation: HexOrientation,
+    size: f32,
+    living: &LivingCells,
+) -> Option<(Vec2, Vec2)> {
+    if living.0.is_empty() {
+        return None;
+    }
+    Some(
+        living
+            .0
+            .iter()
+            .map(|cell| axial_to_screen(orientation, size, cell))
+            .fold(
+                (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+                |(min, max), pos| (min.min(pos), max.max(pos)),
+            ),
+    )
+}
+
+/// Toggles the camera tour on O and, while it runs, moves the camera around a circle
+/// centered on the bounding box of the living cells.
+fn camera_tour(
+    kb: Res<Input<KeyCode>>,
+    time: Res<Time>,
+    orientation: Res<HexOrientation>,
+    hex_size: Res<HexSize>,
+    living: Res<LivingCells>,
+    mut tour: ResMut<CameraTour>,
+    mut query: Query<&mut Transform, With<MainCamera>>,
+) {
+    if kb.just_pressed(KeyCode::O) {
+        tour.enabled = !tour.enabled;
+        info!("camera tour {}", if tour.enabled { "on" } else { "off" });
+    }
+    if !tour.enabled {
+        return;
+    }
+    tour.angle = (tour.angle + TOUR_SPEED * time.delta_seconds()) % std::f32::consts::TAU;
+    let center = living_bounds(*orientation, hex_size.0, &living)
+        .map_or(Vec2::ZERO, |(min, max)| (min + max) / 2.);
+    let target = center + Vec2::new(tour.angle.cos(), tour.angle.sin()) * TOUR_RADIUS * hex_size.0;
+    for mut transform in query.iter_mut() {
+        // Ease towards the circle so turning the tour on, or the pattern's center
+        // moving, doesn't make the view jump.
+        let ease = 1. - (-2. * time.delta_seconds()).exp();
+        let position = transform.translation.truncate().lerp(target, ease);
+        transform.translation = position.extend(transform.translation.z);
+    }
+}
+
+fn player_camera_control(
+    kb: Res<Input<KeyCode>>,
+    time: Res<Time>,
+    wnds: Res<Windows>,
+    limits: Res<CameraLimits>,
+    tour: Res<CameraTour>,
+    mut query: Query<(&Camera, &mut OrthographicProjection, &mut Transform), With<MainCamera>>,
+) {
+    if tour.enabled {
+        return;
+    }
+    let dt = time.delta_seconds();
+    let dist = CAMERA_SPEED_PER_SEC * dt;
+
+    let mut direction = Vec2::ZERO;
+    // Ctrl is reserved for shortcuts like Ctrl+S.
+    if !ctrl_held(&kb) {
+        if kb.pressed(KeyCode::W) || kb.pressed(KeyCode::Up) {
+            direction.y += 1.;
+        }
+        if kb.pressed(KeyCode::S) || kb.pressed(KeyCode::Down) {
+            direction.y -= 1.;
+        }
+        if kb.pressed(KeyCode::A) || kb.pressed(KeyCode::Left) {
+            direction.x -= 1.;
+        }
+        if kb.pressed(KeyCode::D) || kb.pressed(KeyCode::Right) {
+            direction.x += 1.;
+        }
+    }
+    let mut zoom = 0.;
+    if kb.pressed(KeyCode::PageUp) {
+        zoom -= dist;
+    }
+    if kb.pressed(KeyCode::PageDown) {
+        zoom += dist;
+    }
+
+    for (camera, mut projection, mut transform) in query.iter_mut() {
+        transform.translation +=
+            pan_delta(direction, projection.scale, dt, CAMERA_PAN_SPEED).extend(0.);
+        let scale = apply_zoom(projection.scale, zoom, limits.min_zoom, limits.max_zoom);
+        let anchor = cursor_from_center(&wnds, camera);
+        zoom_about(&mut projection, &mut transform, scale, anchor);
+    }
+}
+
+/// How far `input`, a direction from the pan keys, moves a camera at projection scale
+/// `scale` in `dt` seconds. The direction is normalized so diagonals aren't faster
+/// than moving along one axis, and scaled by zoom so the view moves at `speed` screen
+/// pixels per second at any zoom level.
+fn pan_delta(input: Vec2, scale: f32, dt: f32, speed: f32) -> Vec2 {
+    input.normalize_or_zero() * speed * dt * scale
+}
+
+/// `scale` zoomed out by `delta`, or in for negative `delta`, kept within `min` and
+/// `max`. Zoom works in log space so every step changes the scale by the same factor,
+/// and clamping there stops it at a bound at the same rate it approached it.
+fn apply_zoom(scale: f32, delta: f32, min: f32, max: f32) -> f32 {
+    (scale.ln() + delta).clamp(min.ln(), max.ln()).exp()
+}
+
+/// The cursor position relative to the center of the camera's window, in screen
+/// pixels. Zero when the cursor is outside the window.
+fn cursor_from_center(wnds: &Windows, camera: &Camera) -> Vec2 {
+    wnds.get(camera.window)
+        .and_then(|wnd| {
+            let center = Vec2::new(wnd.width(), wnd.height()) / 2.;
+            wnd.cursor_position().map(|pos| pos - center)
+        })
+        .unwrap_or(Vec2::ZERO)
+}
+
+fn gamepad_connections(
+    mut events: EventReader<GamepadEvent>,
+    gamepads: Res<Gamepads>,
+    mut active: ResMut<ActiveGamepad>,
+) {
+    for GamepadEvent(gamepad, event_type) in events.iter() {
+        match event_type {
+            GamepadEventType::Connected if active.0.is_none() => {
+                info!("camera controlled by gamepad {}", gamepad.0);
+                active.0 = Some(*gamepad);
+            }
+            GamepadEventType::Disconnected if active.0 == Some(*gamepad) => {
+                // Hand over to another pad that is still plugged in, if any.
+                active.0 = gamepads.iter().find(|other| *other != gamepad).copied();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Pans with the left stick and zooms with the right stick, on top of whatever the
+/// keyboard does in `player_camera_control`.
+fn gamepad_camera_control(
+    time: Res<Time>,
+    axes: Res<Axis<GamepadAxis>>,
+    active: Res<ActiveGamepad>,
+    limits: Res<CameraLimits>,
+    tour: Res<CameraTour>,
+    mut query: Query<(&mut OrthographicProjection, &mut Transform), With<MainCamera>>,
+) {
+    let gamepad = match active.0 {
+        Some(gamepad) if !tour.enabled => gamepad,
+        _ => return,
+    };
+    let axis = |axis_type| axes.get(GamepadAxis(gamepad, axis_type)).unwrap_or(0.);
+    let stick = Vec2::new(
+        axis(GamepadAxisType::LeftStickX),
+        axis(GamepadAxisType::LeftStickY),
+    );
+    // Clamped so diagonals aren't faster than moving along one axis.
+    let pan = stick.clamp_length_max(1.) * CAMERA_PAN_SPEED * time.delta_seconds();
+    let zoom = axis(GamepadAxisType::RightStickY) * CAMERA_SPEED_PER_SEC * time.delta_seconds();
+    if pan == Vec2::ZERO && zoom == 0. {
+        return;
+    }
+
+    for (mut projection, mut transform) in query.iter_mut() {
+        transform.translation += (pan * projection.scale).extend(0.);
+        let scale = apply_zoom(projection.scale, -zoom, limits.min_zoom, limits.max_zoom);
+        zoom_about(&mut projection, &mut transform, scale, Vec2::ZERO);
+    }
+}
+
+/// Changes the projection scale while keeping the world point at `anchor` (screen
+/// pixels from the window center) in place.
+fn zoom_about(
+    projection: &mut OrthographicProjection,
+    transform: &mut Transform,
+    scale: f32,
+    anchor: Vec2,
+) {
+    // The point under the anchor is at `translation + anchor * scale` before and after.
+    transform.translation += (anchor * (projection.scale - scale)).extend(0.);
+    projection.scale = scale;
+}
+
+/// Zooms towards the cursor with the mouse wheel or a trackpad.
+fn wheel_zoom(
+    mut wheel: EventReader<MouseWheel>,
+    wnds: Res<Windows>,
+    limits: Res<CameraLimits>,
+    tour: Res<CameraTour>,
+    mut query: Query<(&Camera, &mut OrthographicProjection, &mut Transform), With<MainCamera>>,
+) {
+    let lines: f32 = wheel
+        .iter()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / WHEEL_PIXELS_PER_LINE,
+        })
+        .sum();
+    if lines == 0. || tour.enabled {
+        return;
+    }
+    for (camera, mut projection, mut transform) in query.iter_mut() {
+        // Scrolling up zooms in, each notch by the same factor.
+        let delta = -lines * WHEEL_ZOOM_PER_LINE;
+        let scale = apply_zoom(projection.scale, delta, limits.min_zoom, limits.max_zoom);
+        let anchor = cursor_from_center(&wnds, camera);
+        zoom_about(&mut projection, &mut transform, scale, anchor);
+    }
+}
+
+/// Grabs and drags the view while the middle mouse button is held.
+fn drag_camera(
+    buttons: Res<Input<MouseButton>>,
+    mut cursor_moved: EventReader<CursorMoved>,
+    mut last_cursor: Local<Option<Vec2>>,
+    tour: Res<CameraTour>,
+    mut query: Query<(&OrthographicProjection, &mut Transform), With<MainCamera>>,
+) {
+    for event in cursor_moved.iter() {
+        if let Some(last) = *last_cursor {
+            if buttons.pressed(MouseButton::Middle) && !tour.enabled {
+                // Moving the camera against the cursor keeps the grabbed point under it.
+                let delta = event.position - last;
+                for (projection, mut transform) in query.iter_mut() {
+                    transform.translation -= (delta * projection.scale).extend(0.);
+                }
+            }
+        }
+        *last_cursor = Some(event.position);
+    }
+}
+
+fn setup_system(
+    mut commands: Commands,
+    mut shaders: ResMut<Assets<Shader>>,
+    orientation: Res<HexOrientation>,
+    inset: Res<HexInset>,
+    hex_size: Res<HexSize>,
+    topology: Res<BoardTopology>,
+) {
+    let hexagon = shaders.add_sdf_expr(hexagon_sdf(*orientation, inset.0));
+    commands.insert_resource(HexShape(hexagon));
+
+    let mut camera = OrthographicCameraBundle::new_2d();
+    camera.orthographic_projection.scale = DEFAULT_ZOOM;
+    let origin = axial_to_screen(*orientation, hex_size.0, &topology.origin);
+    camera.transform.translation = origin.extend(camera.transform.translation.z);
+    commands.spawn_bundle(camera).insert(MainCamera);
+
+    // A thin ring just inside the default hexagon, so it also shows around dead hexes.
+    let outline = format!(
+        "abs({}) - 1.",
+        hexagon_sdf(*orientation, HexInset::default().0)
+    );
+    commands
+        .spawn_bundle(ShapeBundle {
+            shape: SmudShape {
+                color: CURSOR_COLOR,
+                sdf: shaders.add_sdf_expr(outline),
+                frame: Frame::Quad(HEX_SIZE),
+                fill: SIMPLE_FILL_HANDLE.typed(),
+            },
+            visibility: Visibility { is_visible: false },
+            ..Default::default()
+        })
+        .insert(CursorMarker);
+}
+
+/// The SDF expression for a hexagon of inner radius `inset * HEX_SIZE`.
+fn hexagon_sdf(orientation: HexOrientation, inset: f32) -> String {
+    // Swapping the axes turns the flat-top hexagon 30 degrees onto its corner.
+    let p = match orientation {
+        HexOrientation::FlatTop => "p",
+        HexOrientation::PointyTop => "p.yx",
+    };
+    format!("sd_hexagon({}, {:.3})", p, inset * HEX_SIZE)
+}
+
+/// The SDF expression for a border `width` wide running just inside the edge of a
+/// hexagon of inner radius `inset * HEX_SIZE`.
+fn hexagon_outline_sdf(orientation: HexOrientation, inset: f32, width: f32) -> String {
+    let middle = inset - width / 2. / HEX_SIZE;
+    format!(
+        "abs({}) - {:.3}",
+        hexagon_sdf(orientation, middle),
+        width / 2.
+    )
+}
+
+/// Swaps hexes between the filled and the outlined SDF as they are born, die or get
+/// spawned, and all of them when the theme or `HexInset` changes.
+fn outline_dead_hexes(
+    theme: Res<Theme>,
+    inset: Res<HexInset>,
+    orientation: Res<HexOrientation>,
+    hex_shape: Res<HexShape>,
+    mut outline_shape: ResMut<HexOutlineShape>,
+    mut shaders: ResMut<Assets<Shader>>,
+    born: Query<Entity, Added<Alive>>,
+    died: RemovedComponents<Alive>,
+    spawned: Query<Entity, Added<AxialCoordinate>>,
+    mut query: Query<(&mut SmudShape, Option<&Alive>), With<AxialCoordinate>>,
+) {
+    let rebuild = theme.is_changed() || inset.is_changed();
+    if rebuild {
+        outline_shape.0 = theme
+            .outline
+            .map(|width| shaders.add_sdf_expr(hexagon_outline_sdf(*orientation, inset.0, width)));
+    }
+    let sdf = |alive: bool| match &outline_shape.0 {
+        Some(outline) if !alive => outline.clone(),
+        _ => hex_shape.0.clone(),
+    };
+    if rebuild {
+        for (mut shape, alive) in query.iter_mut() {
+            shape.sdf = sdf(alive.is_some());
+        }
+        return;
+    }
+    if outline_shape.0.is_none() {
+        return;
+    }
+    for entity in born.iter().chain(died.iter()).chain(spawned.iter()) {
+        if let Ok((mut shape, alive)) = query.get_mut(entity) {
+            shape.sdf = sdf(alive.is_some());
+        }
+    }
+}
+
+fn hex_inset_control(kb: Res<Input<KeyCode>>, mut inset: ResMut<HexInset>) {
+    let step = if kb.just_pressed(KeyCode::Key9) {
+        -0.02
+    } else if kb.just_pressed(KeyCode::Key0) {
+        0.02
+    } else {
+        return;
+    };
+    inset.0 = (inset.0 + step).clamp(0.5, SQRT_3 / 2.);
+    info!("hex inset: {:.2}", inset.0);
+}
+
+/// Swaps every hex over to a new SDF when `HexInset` changes.
+fn rebuild_hex_shape(
+    inset: Res<HexInset>,
+    orientation: Res<HexOrientation>,
+    mut shaders: ResMut<Assets<Shader>>,
+    mut hex_shape: ResMut<HexShape>,
+    mut query: Query<&mut SmudShape, With<AxialCoordinate>>,
+) {
+    if !inset.is_changed() || inset.is_added() {
+        return;
+    }
+    hex_shape.0 = shaders.add_sdf_expr(hexagon_sdf(*orientation, inset.0));
+    for mut shape in query.iter_mut() {
+        shape.sdf = hex_shape.0.clone();
+    }
+}
+
+fn hex_size_control(kb: Res<Input<KeyCode>>, mut hex_size: ResMut<HexSize>) {
+    let factor = if kb.just_pressed(KeyCode::Comma) {
+        0.8
+    } else if kb.just_pressed(KeyCode::Period) {
+        1.25
+    } else {
+        return;
+    };
+    hex_size.0 = (hex_size.0 * factor).clamp(MIN_HEX_SIZE, MAX_HEX_SIZE);
+    info!("hex size: {:.1}", hex_size.0);
+}
+
+/// Picks the species painted by the mouse with keys 1 to 4, up to the number of
+/// species in `SpeciesRules`.
+fn species_control(
+    kb: Res<Input<KeyCode>>,
+    rules: Res<SpeciesRules>,
+    mut selected: ResMut<SelectedSpecies>,
+) {
+    let keys = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4];
+    if let Some(kind) = keys.iter().position(|key| kb.just_pressed(*key)) {
+        if kind < rules.0.len() {
+            selected.0 = kind as u8;
+            info!("painting species {}", kind + 1);
+        }
+    }
+}
+
+/// Switches between counting the six adjacent hexes and both rings around a cell on N.
+fn neighbourhood_control(kb: Res<Input<KeyCode>>, mut neighbourhood: ResMut<Neighbourhood>) {
+    if kb.just_pressed(KeyCode::N) {
+        *neighbourhood = match *neighbourhood {
+            Neighbourhood::Ring1 => Neighbourhood::Ring1And2,
+            Neighbourhood::Ring1And2 => Neighbourhood::Ring1,
+        };
+        info!("neighbourhood: {:?}", *neighbourhood);
+    }
+}
+
+fn picking_control(kb: Res<Input<KeyCode>>, mut picking: ResMut<PickingConfig>) {
+    if kb.just_pressed(KeyCode::P) {
+        picking.ignore_gaps = !picking.ignore_gaps;
+        info!("ignore clicks between hexes: {}", picking.ignore_gaps);
+    }
+}
+
+fn brush_control(kb: Res<Input<KeyCode>>, mut brush: ResMut<BrushSize>) {
+    let step = if kb.just_pressed(KeyCode::NumpadAdd) {
+        1
+    } else if kb.just_pressed(KeyCode::NumpadSubtract) {
+        -1
+    } else {
+        return;
+    };
+    brush.0 = (brush.0 + step).clamp(0, MAX_BRUSH_SIZE);
+    info!("brush size: {}", brush.0);
+}
+
+/// Toggles between alive/dead and neighbour count coloring on M.
+fn color_mode_control(kb: Res<Input<KeyCode>>, mut color_mode: ResMut<ColorMode>) {
+    if kb.just_pressed(KeyCode::M) {
+        *color_mode = match *color_mode {
+            ColorMode::AliveDead => ColorMode::NeighbourCount,
+            ColorMode::NeighbourCount => ColorMode::AliveDead,
+        };
+    }
+}
+
+/// Switches to the next of `THEMES` on T and keeps `ClearColor` on the theme's background.
+fn theme_control(
+    kb: Res<Input<KeyCode>>,
+    mut theme: ResMut<Theme>,
+    mut clear_color: ResMut<ClearColor>,
+) {
+    if kb.just_pressed(KeyCode::T) {
+        // Found by name, since a loaded session can switch the theme too.
+        let index = THEMES
+            .iter()
+            .position(|t| t.name == theme.name)
+            .unwrap_or(0);
+        *theme = THEMES[(index + 1) % THEMES.len()];
+        info!("theme: {}", theme.name);
+    }
+    if theme.is_changed() {
+        clear_color.0 = theme.background;
+    }
+}
+
+/// Moves and scales the spawned hexes to match a changed `HexSize`.
+fn rescale_hexes(
+    hex_size: Res<HexSize>,
+    orientation: Res<HexOrientation>,
+    mut query: Query<(&AxialCoordinate, &mut Transform)>,
+) {
+    if !hex_size.is_changed() {
+        return;
+    }
+    for (coord, mut transform) in query.iter_mut() {
+        transform.translation = (axial_to_screen(*orientation, hex_size.0, coord), 0.).into();
+        transform.scale = Vec3::splat(hex_size.0 / HEX_SIZE);
+    }
+}
+
+/// Spawns the hexes that scroll into view and despawns the ones that scroll out, so the
+/// board has no edge to pan off of. Zoomed far out only living hexes are spawned.
+fn visible_hex_streaming(
+    mut commands: Commands,
+    q_camera: Query<(&OrthographicProjection, &Transform), With<MainCamera>>,
+    orientation: Res<HexOrientation>,
+    hex_size: Res<HexSize>,
+    topology: Res<BoardTopology>,
+    living: Res<LivingCells>,
+    ages: Res<CellAges>,
+    species: Res<CellSpecies>,
+    hex_shape: Res<HexShape>,
+    mut hex_map: ResMut<HexMap>,
+    mut last_view: Local<Option<(IVec2, IVec2)>>,
+) {
+    let orientation = *orientation;
+    let size = hex_size.0;
+    let (projection, transform) = q_camera.single();
+    let center = transform.translation.truncate();
+    // Padded by half a hex so the ones cut off at the window edge are spawned too.
+    let dimensions = hex_dimensions(orientation, size);
+    let padding = Vec2::new(dimensions.width, dimensions.height) / 2.;
+    let min = center + Vec2::new(projection.left, projection.bottom) * projection.scale - padding;
+    let max = center + Vec2::new(projection.right, projection.top) * projection.scale + padding;
+
+    // The view is a rectangle in world space, so its axial bounds are found at the corners.
+    let corners = [min, Vec2::new(min.x, max.y), Vec2::new(max.x, min.y), max]
+        .map(|corner| screen_to_cube_float(orientation, size, corner).truncate());
+    let lo = corners.iter().fold(Vec2::splat(f32::MAX), |a, b| a.min(*b));
+    let hi = corners.iter().fold(Vec2::splat(f32::MIN), |a, b| a.max(*b));
+    let (lo, hi) = (lo.floor().as_ivec2(), hi.ceil().as_ivec2());
+    let extent = hi - lo + IVec2::ONE;
+    let dense = extent.x.saturating_mul(extent.y) <= MAX_STREAMED_HEXES;
+    if *last_view == Some((lo, hi)) && (dense || !living.is_changed()) {
+        return;
+    }
+    *last_view = Some((lo, hi));
+
+    let in_view = |coord: &AxialCoordinate| {
+        let pos = axial_to_screen(orientation, size, coord);
+        pos.cmpge(min).all() && pos.cmple(max).all()
+    };
+    let visible: HashSet<AxialCoordinate> = if dense {
+        (lo.x..=hi.x)
+            .flat_map(|q| (lo.y..=hi.y).map(move |r| AxialCoordinate::new(q, r)))
+            .filter(|coord| topology.contains(coord) && in_view(coord))
+            .collect()
+    } else {
+        living
+            .0
+            .iter()
+            .filter(|coord| in_view(coord))
+            .copied()
+            .collect()
+    };
+
+    hex_map.0.retain(|coord, entity| {
+        let keep = visible.contains(coord);
+        if !keep {
+            commands.entity(*entity).despawn();
+        }
+        keep
+    });
+    for coord in visible {
+        if hex_map.0.contains_key(&coord) {
+            continue;
+        }
+        let mut hex = commands.spawn_bundle(ShapeBundle {
+            shape: SmudShape {
+                color: Color::BLACK,
+                sdf: hex_shape.0.clone(),
+                frame: Frame::Quad(HEX_SIZE),
+                fill: SIMPLE_FILL_HANDLE.typed(),
+            },
+            transform: Transform::from_translation(
+                (axial_to_screen(orientation, size, &coord), 0.).into(),
+            )
+            .with_scale(Vec3::splat(size / HEX_SIZE)),
+            ..Default::default()
+        });
+        hex.insert(coord);
+        // Cells keep living while off-screen, so they come back alive when scrolled to.
+        if living.0.contains(&coord) {
+            let age = ages.0.get(&coord).copied().unwrap_or(0);
+            let kind = species.0.get(&coord).copied().unwrap_or(0);
+            hex.insert_bundle((Alive, Age(age), Species(kind)));
+        }
+        hex_map.0.insert(coord, hex.id());
+    }
+}
+
+/// Toggles `q,r` labels on the spawned hexes with G, for debugging coordinate math.
+/// Labels are hidden while zoomed out past `LABEL_MAX_SCALE`.
+fn coordinate_overlay(
+    mut commands: Commands,
+    kb: Res<Input<KeyCode>>,
+    asset_server: Res<AssetServer>,
+    orientation: Res<HexOrientation>,
+    hex_size: Res<HexSize>,
+    hex_map: Res<HexMap>,
+    q_camera: Query<&OrthographicProjection, With<MainCamera>>,
+    mut enabled: Local<bool>,
+    mut labels: Local<HashMap<AxialCoordinate, Entity>>,
+) {
+    if kb.just_pressed(KeyCode::G) {
+        *enabled = !*enabled;
+    }
+    let shown = *enabled && q_camera.single().scale < LABEL_MAX_SCALE;
+    labels.retain(|coord, entity| {
+        // Labels are respawned rather than moved when the hexes are resized.
+        let keep = shown && !hex_size.is_changed() && hex_map.0.contains_key(coord);
+        if !keep {
+            commands.entity(*entity).despawn();
+        }
+        keep
+    });
+    if !shown {
+        return;
+    }
+    let style = TextStyle {
+        font: asset_server.load(LABEL_FONT),
+        font_size: 6.,
+        color: Color::WHITE,
+    };
+    let alignment = TextAlignment {
+        vertical: VerticalAlign::Center,
+        horizontal: HorizontalAlign::Center,
+    };
+    for coord in hex_map.0.keys() {
+        if labels.contains_key(coord) {
+            continue;
+        }
+        let label = commands
+            .spawn_bundle(Text2dBundle {
+                text: Text::with_section(
+                    format!("{},{}", coord.q(), coord.r()),
+                    style.clone(),
+                    alignment,
+                ),
+                transform: Transform::from_translation(
+                    (axial_to_screen(*orientation, hex_size.0, coord), 1.).into(),
+                ),
+                ..Default::default()
+            })
+            .id();
+        labels.insert(*coord, label);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placing_a_pattern_drops_overflowing_cells() {
+        let cells = pattern::decode("2\n2147483647,0\n-1,0").unwrap();
+        let placed: Vec<_> = place_pattern(AxialCoordinate::new(1, 0), &cells).collect();
+        assert_eq!(placed, vec![AxialCoordinate::new(0, 0)]);
+        let cells = [AxialCoordinate::new(0, i32::MAX)];
+        assert_eq!(place_pattern(AxialCoordinate::new(1, 0), &cells).count(), 0);
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_board() {
+        let coords: Vec<AxialCoordinate> = AxialCoordinate::new(0, 0).spiral(5).take(100).collect();
+        let density = SpawnConfig::default().density;
+        let first = random_cells(&mut StdRng::seed_from_u64(42), coords.clone(), density);
+        let second = random_cells(&mut StdRng::seed_from_u64(42), coords.clone(), density);
+        assert!(!first.is_empty());
+        assert_eq!(first, second);
+        let other = random_cells(&mut StdRng::seed_from_u64(43), coords, density);
+        assert_ne!(first, other);
+    }
+
+    #[test]
+    fn wrapped_neighbours_resolve_across_the_seam() {
+        for origin in [AxialCoordinate::new(0, 0), AxialCoordinate::new(3, -2)] {
+            let topology = BoardTopology {
+                wrap: Some((8, 6)),
+                shape: BoardShape::Unbounded,
+                origin,
+            };
+            for col in -4..4 {
+                for row in -3..3 {
+                    let cell = topology.globalize(offset_to_axial(col, row));
+                    assert!(topology.contains(&cell));
+                    for neighbour in cell.neighbour_iter() {
+                        let resolved = topology.resolve(neighbour);
+                        assert!(topology.contains(&resolved));
+                        // Stepping back from the resolved hex leads to `cell` again.
+                        assert!(resolved
+                            .neighbour_iter()
+                            .any(|back| topology.resolve(back) == cell));
+                    }
+                }
+            }
+            // The upper right neighbour of the last column is in the first one.
+            let edge = topology.globalize(offset_to_axial(3, 0));
+            let across = topology.localize(topology.resolve(edge.neighbour_iter().next().unwrap()));
+            assert_eq!(axial_to_offset(&across).0, -4);
+        }
+    }
+
+    #[test]
+    fn edge_cells_count_hexes_beyond_the_edge_as_dead() {
+        let rules = LifeRules::default();
+        // What `game_of_life` does: count everything next to life, then drop what's
+        // off the board.
+        let tick = |topology: &BoardTopology, alive: &HashSet<AxialCoordinate>| {
+            let mut next = next_generation_with(alive, &rules, Neighbourhood::Ring1, |coord| {
+                topology.resolve(coord)
+            });
+            next.retain(|cell| topology.contains(cell));
+            next
+        };
+        let bounded = BoardTopology {
+            shape: BoardShape::Rectangle { cols: 8, rows: 6 },
+            ..Default::default()
+        };
+        let wrapped = BoardTopology {
+            wrap: Some((8, 6)),
+            ..Default::default()
+        };
+        let cells: Vec<AxialCoordinate> = (-4..4)
+            .flat_map(|col| (-3..3).map(move |row| offset_to_axial(col, row)))
+            .collect();
+        assert!(cells
+            .iter()
+            .all(|cell| bounded.contains(cell) && wrapped.contains(cell)));
+
+        // Leaving the hexes beyond the edge out of the counts gives the same board.
+        let alive = random_cells(&mut StdRng::seed_from_u64(7), cells.clone(), 0.4);
+        let excluded: HashSet<AxialCoordinate> = cells
+            .iter()
+            .copied()
+            .filter(|cell| {
+                let n = cell
+                    .neighbour_iter()
+                    .filter(|n| bounded.contains(n) && alive.contains(n))
+                    .count() as u8;
+                if alive.contains(cell) {
+                    rules.survive.contains(&n)
+                } else {
+                    rules.born.contains(&n)
+                }
+            })
+            .collect();
+        assert_eq!(tick(&bounded, &alive), excluded);
+
+        // The hexes past the last column are dead on the bounded board, but are the
+        // living first column on the wrapped one.
+        let edge = offset_to_axial(3, 0);
+        let across: HashSet<AxialCoordinate> = edge
+            .neighbour_iter()
+            .filter(|n| !bounded.contains(n))
+            .map(|n| wrapped.resolve(n))
+            .collect();
+        assert_eq!(across.len(), 2);
+        assert!(across.iter().all(|cell| axial_to_offset(cell).0 == -4));
+        assert!(!tick(&bounded, &across).contains(&edge));
+        assert!(tick(&wrapped, &across).contains(&edge));
+    }
+
+    #[test]
+    fn pan_delta_normalizes_diagonals() {
+        let along = pan_delta(Vec2::new(1., 0.), 1., 0.5, 100.);
+        assert!((along - Vec2::new(50., 0.)).length() < 1e-4);
+        let diagonal = pan_delta(Vec2::new(1., -1.), 1., 0.5, 100.);
+        assert!((diagonal.length() - 50.).abs() < 1e-4);
+        assert_eq!(pan_delta(Vec2::ZERO, 1., 0.5, 100.), Vec2::ZERO);
+    }
+
+    #[test]
+    fn pan_delta_scales_with_zoom() {
+        let near = pan_delta(Vec2::new(0., 1.), 1., 0.1, 200.);
+        let far = pan_delta(Vec2::new(0., 1.), 4., 0.1, 200.);
+        assert!((far - near * 4.).length() < 1e-4);
+    }
+
+    #[test]
+    fn apply_zoom_clamps_at_both_bounds() {
+        assert!((apply_zoom(1., 0., 0.5, 4.) - 1.).abs() < 1e-5);
+        assert!((apply_zoom(1., 2f32.ln(), 0.5, 4.) - 2.).abs() < 1e-5);
+        assert!((apply_zoom(1., 10., 0.5, 4.) - 4.).abs() < 1e-5);
+        assert!((apply_zoom(1., -10., 0.5, 4.) - 0.5).abs() < 1e-5);
+        // Already at a bound, zooming further past it stays there.
+        assert!((apply_zoom(4., 1., 0.5, 4.) - 4.).abs() < 1e-5);
+        assert!((apply_zoom(0.5, -1., 0.5, 4.) - 0.5).abs() < 1e-5);
+    }
 }
@@ -0,0 +1,173 @@
+use bevy::{
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+use hex_of_life::hex::{axial_to_screen, HexOrientation};
+
+use crate::{living_bounds, HexSize, LivingCells, MainCamera, Theme};
+
+// Side of the square minimap in logical pixels, and its distance from the window corner.
+const MINIMAP_SIZE: u32 = 160;
+const MINIMAP_MARGIN: f32 = 5.;
+// Seconds between redraws of the minimap.
+const MINIMAP_PERIOD: f32 = 0.1;
+
+/// A map of all living cells and the camera's view in the bottom right corner. Clicking
+/// it moves the camera to the clicked spot.
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MinimapView>()
+            .add_startup_system(setup_minimap)
+            .add_system(draw_minimap)
+            .add_system(click_minimap);
+    }
+}
+
+/// The world-space square the minimap showed when it was last drawn.
+#[derive(Default)]
+struct MinimapView {
+    min: Vec2,
+    max: Vec2,
+}
+
+struct MinimapImage(Handle<Image>);
+
+/// Whether the cursor is over the minimap, where clicks move the camera instead of
+/// editing the board.
+pub fn contains_cursor(wnds: &Windows) -> bool {
+    wnds.get_primary()
+        .and_then(|wnd| Some(minimap_uv(wnd.cursor_position()?, wnd.width())))
+        .is_some_and(|uv| uv.cmpge(Vec2::ZERO).all() && uv.cmple(Vec2::ONE).all())
+}
+
+/// Where `cursor`, measured from the bottom left of a window `width` wide, falls on
+/// the minimap, from (0, 0) at its bottom left to (1, 1) at its top right.
+fn minimap_uv(cursor: Vec2, width: f32) -> Vec2 {
+    let size = MINIMAP_SIZE as f32;
+    let bottom_left = Vec2::new(width - MINIMAP_MARGIN - size, MINIMAP_MARGIN);
+    (cursor - bottom_left) / size
+}
+
+fn setup_minimap(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let image = images.add(Image::new_fill(
+        Extent3d {
+            width: MINIMAP_SIZE,
+            height: MINIMAP_SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 255],
+        TextureFormat::Rgba8UnormSrgb,
+    ));
+    commands.spawn_bundle(ImageBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: Rect {
+                right: Val::Px(MINIMAP_MARGIN),
+                bottom: Val::Px(MINIMAP_MARGIN),
+                ..Default::default()
+            },
+            size: Size::new(Val::Px(MINIMAP_SIZE as f32), Val::Px(MINIMAP_SIZE as f32)),
+            ..Default::default()
+        },
+        image: UiImage(image.clone()),
+        ..Default::default()
+    });
+    commands.insert_resource(MinimapImage(image));
+}
+
+/// Redraws the minimap around the living cells and the camera's view, whichever
+/// reaches further.
+fn draw_minimap(
+    time: Res<Time>,
+    theme: Res<Theme>,
+    orientation: Res<HexOrientation>,
+    hex_size: Res<HexSize>,
+    living: Res<LivingCells>,
+    minimap: Res<MinimapImage>,
+    mut images: ResMut<Assets<Image>>,
+    mut view: ResMut<MinimapView>,
+    q_camera: Query<(&OrthographicProjection, &Transform), With<MainCamera>>,
+    mut since_drawn: Local<f32>,
+) {
+    *since_drawn += time.delta_seconds();
+    if *since_drawn < MINIMAP_PERIOD {
+        return;
+    }
+    *since_drawn = 0.;
+    let (projection, transform) = q_camera.single();
+    let center = transform.translation.truncate();
+    let half_view = Vec2::new(projection.right, projection.top) * projection.scale;
+    let (view_min, view_max) = (center - half_view, center + half_view);
+    let (min, max) = match living_bounds(*orientation, hex_size.0, &living) {
+        Some((min, max)) => (min.min(view_min), max.max(view_max)),
+        None => (view_min, view_max),
+    };
+    // Square and padded, so the map isn't stretched and nothing sits on its edge.
+    let side = (max - min).max_element() * 1.1;
+    let middle = (min + max) / 2.;
+    view.min = middle - Vec2::splat(side / 2.);
+    view.max = middle + Vec2::splat(side / 2.);
+
+    let image = match images.get_mut(&minimap.0) {
+        Some(image) => image,
+        None => return,
+    };
+    let size = MINIMAP_SIZE as usize;
+    let to_pixel = |pos: Vec2| {
+        let uv = (pos - view.min) / side;
+        // Rows run top to bottom, world y bottom to top.
+        let x = (uv.x * size as f32) as usize;
+        let y = ((1. - uv.y) * size as f32) as usize;
+        (x.min(size - 1), y.min(size - 1))
+    };
+    let rgba = |color: Color| color.as_rgba_f32().map(|c| (c.clamp(0., 1.) * 255.) as u8);
+    let mut pixels = rgba(theme.dead).repeat(size * size);
+    let mut plot = |(x, y): (usize, usize), color: [u8; 4]| {
+        pixels[(y * size + x) * 4..][..4].copy_from_slice(&color);
+    };
+    let alive = rgba(theme.alive);
+    for cell in living.0.iter() {
+        plot(
+            to_pixel(axial_to_screen(*orientation, hex_size.0, cell)),
+            alive,
+        );
+    }
+    let (left, top) = to_pixel(Vec2::new(view_min.x, view_max.y));
+    let (right, bottom) = to_pixel(Vec2::new(view_max.x, view_min.y));
+    let outline = rgba(theme.hover + Color::rgb(0.5, 0.5, 0.5));
+    for x in left..=right {
+        plot((x, top), outline);
+        plot((x, bottom), outline);
+    }
+    for y in top..=bottom {
+        plot((left, y), outline);
+        plot((right, y), outline);
+    }
+    image.data = pixels;
+}
+
+fn click_minimap(
+    wnds: Res<Windows>,
+    buttons: Res<Input<MouseButton>>,
+    view: Res<MinimapView>,
+    mut q_camera: Query<&mut Transform, With<MainCamera>>,
+) {
+    if !buttons.just_pressed(MouseButton::Left) || !contains_cursor(&wnds) {
+        return;
+    }
+    let wnd = match wnds.get_primary() {
+        Some(wnd) => wnd,
+        None => return,
+    };
+    let uv = match wnd.cursor_position() {
+        Some(cursor) => minimap_uv(cursor, wnd.width()),
+        None => return,
+    };
+    let target = view.min + uv * (view.max - view.min);
+    for mut transform in q_camera.iter_mut() {
+        transform.translation = target.extend(transform.translation.z);
+    }
+}
@@ -0,0 +1,243 @@
+use std::fmt;
+
+use crate::hex::{offset_to_axial, AxialCoordinate};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PatternError {
+    MissingHeader,
+    InvalidHeader(String),
+    InvalidLine { line: usize, content: String },
+    CountMismatch { expected: usize, found: usize },
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PatternError::MissingHeader => write!(f, "missing cell count header"),
+            PatternError::InvalidHeader(header) => {
+                write!(f, "invalid cell count header {:?}", header)
+            }
+            PatternError::InvalidLine { line, content } => {
+                write!(f, "line {}: expected `q,r`, found {:?}", line, content)
+            }
+            PatternError::CountMismatch { expected, found } => {
+                write!(f, "header says {} cells but found {}", expected, found)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+/// Encodes `cells` in the `.hex` pattern format: a header line with the number of
+/// cells followed by one `q,r` pair per line. Coordinates are shifted so that the
+/// smallest q and r are both zero.
+pub fn encode(cells: &[AxialCoordinate]) -> String {
+    let min_q = cells.iter().map(|c| c.q()).min().unwrap_or(0);
+    let min_r = cells.iter().map(|c| c.r()).min().unwrap_or(0);
+    let mut normalized: Vec<(i32, i32)> = cells
+        .iter()
+        .map(|c| (c.q() - min_q, c.r() - min_r))
+        .collect();
+    normalized.sort_unstable();
+
+    let mut out = format!("{}\n", normalized.len());
+    for (q, r) in normalized {
+        out.push_str(&format!("{}\n", AxialCoordinate::new(q, r)));
+    }
+    out
+}
+
+/// Decodes the `.hex` pattern format written by [`encode`]. Blank lines are ignored.
+pub fn decode(input: &str) -> Result<Vec<AxialCoordinate>, PatternError> {
+    let mut lines = input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty());
+
+    let (_, header) = lines.next().ok_or(PatternError::MissingHeader)?;
+    let expected: usize = header
+        .parse()
+        .map_err(|_| PatternError::InvalidHeader(header.to_string()))?;
+
+    let cells = lines
+        .map(|(line, content)| {
+            content.parse().map_err(|_| PatternError::InvalidLine {
+                line,
+                content: content.to_string(),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if cells.len() != expected {
+        return Err(PatternError::CountMismatch {
+            expected,
+            found: cells.len(),
+        });
+    }
+    Ok(cells)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RleError {
+    MissingHeader,
+    InvalidHeader(String),
+    InvalidToken {
+        line: usize,
+        token: char,
+    },
+    /// A run count too large to be a number, or living cells past the header's width.
+    RunTooLong {
+        line: usize,
+    },
+}
+
+impl fmt::Display for RleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RleError::MissingHeader => write!(f, "missing `x = .., y = ..` header"),
+            RleError::InvalidHeader(header) => write!(f, "invalid header {:?}", header),
+            RleError::InvalidToken { line, token } => {
+                write!(f, "line {}: unexpected {:?}", line, token)
+            }
+            RleError::RunTooLong { line } => {
+                write!(f, "line {}: run goes past the edge of the pattern", line)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RleError {}
+
+/// Parses a pattern in the run-length encoded `.rle` format used for square-grid Life.
+///
+/// Square cells map onto hexes by treating each `(x, y)` as odd-q offset coordinates,
+/// with `y` flipped so the pattern isn't drawn upside down. The top left cell of the
+/// pattern is the origin. `#` comment lines are skipped and the rule in the header,
+/// if any, is ignored.
+pub fn parse_rle(input: &str) -> Result<Vec<AxialCoordinate>, RleError> {
+    let mut lines = input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+    let (_, header) = lines.next().ok_or(RleError::MissingHeader)?;
+    let (width, _) =
+        parse_rle_header(header).ok_or_else(|| RleError::InvalidHeader(header.to_string()))?;
+
+    let mut cells = Vec::new();
+    let (mut x, mut y): (i32, i32) = (0, 0);
+    let mut run: Option<i32> = None;
+    'lines: for (line, content) in lines {
+        for token in content.chars() {
+            let count = run.unwrap_or(1);
+            let too_long = RleError::RunTooLong { line };
+            match token {
+                '0'..='9' => {
+                    let digit = token.to_digit(10).unwrap() as i32;
+                    run = Some(
+                        run.unwrap_or(0)
+                            .checked_mul(10)
+                            .and_then(|run| run.checked_add(digit))
+                            .ok_or(too_long)?,
+                    );
+                    continue;
+                }
+                'b' => x = x.checked_add(count).ok_or(too_long)?,
+                'o' => {
+                    // Living cells have to fit the header, which also bounds how many
+                    // a single oversized run can add.
+                    let end = x.checked_add(count).filter(|end| *end <= width);
+                    let end = end.ok_or(too_long)?;
+                    cells.extend((x..end).map(|col| offset_to_axial(col, -y)));
+                    x = end;
+                }
+                '$' => {
+                    x = 0;
+                    y = y.checked_add(count).ok_or(too_long)?;
+                }
+                '!' => break 'lines,
+                token if token.is_whitespace() => {}
+                token => return Err(RleError::InvalidToken { line, token }),
+            }
+            run = None;
+        }
+    }
+    Ok(cells)
+}
+
+/// The `x` and `y` of a `x = 3, y = 2, rule = B3/S23` header.
+fn parse_rle_header(header: &str) -> Option<(i32, i32)> {
+    let mut size = (None, None);
+    for field in header.split(',') {
+        let (key, value) = field.split_once('=')?;
+        match key.trim() {
+            "x" => size.0 = value.trim().parse().ok(),
+            "y" => size.1 = value.trim().parse().ok(),
+            _ => {}
+        }
+    }
+    Some((size.0?, size.1?))
+}
+
+/// Names accepted by `builtin_pattern`.
+pub const BUILTIN_PATTERNS: [&str; 3] = ["blinker", "clock", "glider"];
+
+/// A few small patterns for the default B2/S2 rules, around the origin. These rules
+/// have no still lifes of a handful of cells, so the library is oscillators and a
+/// spaceship:
+///
+/// - `blinker`: two cells flipping to the pair beside them, period 2.
+/// - `clock`: four cells cycling with period 4.
+/// - `glider`: seven cells travelling two hexes every three generations.
+pub fn builtin_pattern(name: &str) -> Option<Vec<AxialCoordinate>> {
+    let cells: &[(i32, i32)] = match name {
+        "blinker" => &[(0, 0), (0, 1)],
+        "clock" => &[(0, 0), (0, 1), (1, 2), (2, 2)],
+        "glider" => &[(0, 0), (0, 1), (1, 0), (2, -2), (2, 1), (3, -2), (3, 0)],
+        _ => return None,
+    };
+    Some(
+        cells
+            .iter()
+            .map(|(q, r)| AxialCoordinate::new(*q, *r))
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rle_reads_runs() {
+        let cells = parse_rle("#C a comment\nx = 3, y = 2\n2o$b2o!").unwrap();
+        let expected: Vec<AxialCoordinate> = [(0, 0), (1, 0), (1, 1), (2, 1)]
+            .iter()
+            .map(|(col, row)| offset_to_axial(*col, -*row))
+            .collect();
+        assert_eq!(cells, expected);
+    }
+
+    #[test]
+    fn parse_rle_rejects_oversized_runs() {
+        assert_eq!(
+            parse_rle("x = 3, y = 1\n99999999999o!"),
+            Err(RleError::RunTooLong { line: 2 })
+        );
+        assert_eq!(
+            parse_rle("x = 3, y = 1\n4o!"),
+            Err(RleError::RunTooLong { line: 2 })
+        );
+        assert_eq!(
+            parse_rle("x = 3, y = 1\n2147483647b2o!"),
+            Err(RleError::RunTooLong { line: 2 })
+        );
+        assert_eq!(
+            parse_rle("x = 3, y = 1\no2147483647$2147483647$o!"),
+            Err(RleError::RunTooLong { line: 2 })
+        );
+    }
+}
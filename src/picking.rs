@@ -0,0 +1,14 @@
+use bevy::prelude::*;
+
+use crate::hex::AxialCoordinate;
+
+/// Sent for every mouse button pressed over a spawned hex, other than over the minimap.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct HexClicked {
+    /// The spawned hex entity that was clicked.
+    pub entity: Entity,
+    pub coord: AxialCoordinate,
+    pub button: MouseButton,
+    /// The cursor position in world space, not necessarily at the center of the hex.
+    pub world_pos: Vec2,
+}
@@ -0,0 +1,178 @@
+use std::{
+    fmt::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+use hex_of_life::hex::*;
+
+use crate::{
+    hex_color, Age, BoardTopology, CellAges, CellSpecies, HexInset, HexSize, LivingCells,
+    MainCamera, Species, Theme, HEX_SIZE,
+};
+
+const SVG_FILE: &str = "out.svg";
+// Space left around the living cells in SVG exports, in hexes.
+const SVG_MARGIN: f32 = 2.;
+
+/// Saves the board as seen by the main camera to `screenshot_<timestamp>.png` on F12,
+/// and the living cells as vector shapes to `out.svg` on F10.
+///
+/// Bevy has no way to read the rendered frame back yet, so the image is drawn on the
+/// CPU from the same cells, colors and camera the renderer uses.
+pub struct ScreenshotPlugin;
+
+impl Plugin for ScreenshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(capture_screenshot).add_system(export_svg);
+    }
+}
+
+fn capture_screenshot(
+    kb: Res<Input<KeyCode>>,
+    wnds: Res<Windows>,
+    theme: Res<Theme>,
+    orientation: Res<HexOrientation>,
+    hex_size: Res<HexSize>,
+    inset: Res<HexInset>,
+    topology: Res<BoardTopology>,
+    living: Res<LivingCells>,
+    ages: Res<CellAges>,
+    species: Res<CellSpecies>,
+    q_camera: Query<(&OrthographicProjection, &Transform), With<MainCamera>>,
+) {
+    if !kb.just_pressed(KeyCode::F12) {
+        return;
+    }
+    let wnd = match wnds.get_primary() {
+        Some(wnd) => wnd,
+        None => return,
+    };
+    let (width, height) = (wnd.physical_width(), wnd.physical_height());
+    let (projection, transform) = q_camera.single();
+    let center = transform.translation.truncate();
+    let pixel_size = projection.scale / wnd.scale_factor() as f32;
+    let shape_scale = hex_size.0 / HEX_SIZE;
+
+    let image = image::RgbImage::from_fn(width, height, |x, y| {
+        // Image rows run top to bottom, world y runs bottom to top.
+        let offset = Vec2::new(x as f32 - width as f32 / 2., height as f32 / 2. - y as f32);
+        let world_pos = center + offset * pixel_size;
+        let coord = screen_to_axial(*orientation, hex_size.0, world_pos);
+        let local = (world_pos - axial_to_screen(*orientation, hex_size.0, &coord)) / shape_scale;
+        let color =
+            if topology.contains(&coord) && in_hexagon(*orientation, local, inset.0 * HEX_SIZE) {
+                let cell = living.0.contains(&coord).then(|| {
+                    (
+                        Age(ages.0.get(&coord).copied().unwrap_or(0)),
+                        Species(species.0.get(&coord).copied().unwrap_or(0)),
+                    )
+                });
+                hex_color(&theme, cell.as_ref().map(|(age, kind)| (age, kind)), false)
+            } else {
+                theme.background
+            };
+        let [r, g, b, _] = color.as_rgba_f32();
+        image::Rgb([r, g, b].map(|c| (c.clamp(0., 1.) * 255.).round() as u8))
+    });
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let path = format!("screenshot_{}.png", timestamp);
+    match image.save(&path) {
+        Ok(()) => info!("saved screenshot to {}", path),
+        Err(err) => error!("failed to save {}: {}", path, err),
+    }
+}
+
+/// Writes one hexagon per living cell, in its species' color on the background color.
+fn export_svg(
+    kb: Res<Input<KeyCode>>,
+    theme: Res<Theme>,
+    orientation: Res<HexOrientation>,
+    hex_size: Res<HexSize>,
+    inset: Res<HexInset>,
+    living: Res<LivingCells>,
+    species: Res<CellSpecies>,
+) {
+    if !kb.just_pressed(KeyCode::F10) {
+        return;
+    }
+    let size = hex_size.0;
+    // Corners of the hexagon as drawn, rotated onto an edge for pointy-top hexes.
+    let circumradius = inset.0 * size * 2. / SQRT_3;
+    let first_corner = match *orientation {
+        HexOrientation::FlatTop => 0.,
+        HexOrientation::PointyTop => 30.,
+    };
+    let corners: Vec<Vec2> = (0..6)
+        .map(|i| {
+            let angle = (first_corner + 60. * i as f32).to_radians();
+            Vec2::new(angle.cos(), angle.sin()) * circumradius
+        })
+        .collect();
+    // SVG's y axis points down, the world's up.
+    let centers: Vec<(AxialCoordinate, Vec2)> = living
+        .0
+        .iter()
+        .map(|coord| {
+            let pos = axial_to_screen(*orientation, size, coord);
+            (*coord, Vec2::new(pos.x, -pos.y))
+        })
+        .collect();
+    let margin = Vec2::splat(SVG_MARGIN * 2. * size);
+    let (min, max) = centers.iter().fold(
+        (Vec2::splat(f32::MAX), Vec2::splat(f32::MIN)),
+        |(min, max), (_, pos)| (min.min(*pos), max.max(*pos)),
+    );
+    let (min, max) = if centers.is_empty() {
+        (-margin, margin)
+    } else {
+        (min - margin, max + margin)
+    };
+    let extent = max - min;
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}">"#,
+        min.x, min.y, extent.x, extent.y
+    );
+    let _ = writeln!(
+        svg,
+        r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#,
+        min.x,
+        min.y,
+        extent.x,
+        extent.y,
+        svg_color(theme.background)
+    );
+    for (coord, center) in centers {
+        let kind = species.0.get(&coord).copied().unwrap_or(0);
+        let color = hex_color(&theme, Some((&Age(0), &Species(kind))), false);
+        let points: Vec<String> = corners
+            .iter()
+            .map(|corner| format!("{:.2},{:.2}", center.x + corner.x, center.y - corner.y))
+            .collect();
+        let _ = writeln!(
+            svg,
+            r#"<polygon points="{}" fill="{}"/>"#,
+            points.join(" "),
+            svg_color(color)
+        );
+    }
+    svg.push_str("</svg>\n");
+
+    match std::fs::write(SVG_FILE, svg) {
+        Ok(()) => info!("exported {} cells to {}", living.0.len(), SVG_FILE),
+        Err(err) => error!("failed to write {}: {}", SVG_FILE, err),
+    }
+}
+
+/// `color` as an SVG `#rrggbb` color.
+fn svg_color(color: Color) -> String {
+    let [r, g, b, _] = color.as_rgba_f32();
+    let [r, g, b] = [r, g, b].map(|c| (c.clamp(0., 1.) * 255.).round() as u8);
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
@@ -0,0 +1,182 @@
+use std::{fs, marker::PhantomData};
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use hex_of_life::{hex::AxialCoordinate, life::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    CellAges, CellSpecies, Generation, HexSize, HistoryBuffer, LivingCells, MainCamera, SimHistory,
+    StateHistory, Theme, THEMES,
+};
+
+const SESSION_FILE: &str = "session.ron";
+
+/// Saves the board, rules, camera, hex size, theme and generation to `session.ron` on
+/// F6 and restores them on F9. With `autoload` set, a session left behind by an earlier
+/// run is restored at startup too.
+pub struct SessionPlugin {
+    pub autoload: bool,
+}
+
+impl Plugin for SessionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(save_session).add_system(load_session);
+        if self.autoload {
+            // After `setup_system` has spawned the camera.
+            app.add_startup_system_to_stage(StartupStage::PostStartup, autoload_session);
+        }
+    }
+}
+
+/// Everything written to the session file.
+#[derive(Serialize, Deserialize)]
+struct Session {
+    living: Vec<AxialCoordinate>,
+    ages: Vec<(AxialCoordinate, u32)>,
+    species: Vec<(AxialCoordinate, u8)>,
+    rules: Vec<LifeRules>,
+    camera: [f32; 2],
+    zoom: f32,
+    hex_size: f32,
+    theme: String,
+    generation: u64,
+}
+
+/// The parts of the app a session saves and restores.
+#[derive(SystemParam)]
+struct SessionState<'w, 's> {
+    living: ResMut<'w, LivingCells>,
+    ages: ResMut<'w, CellAges>,
+    species: ResMut<'w, CellSpecies>,
+    rules: ResMut<'w, SpeciesRules>,
+    hex_size: ResMut<'w, HexSize>,
+    theme: ResMut<'w, Theme>,
+    generation: ResMut<'w, Generation>,
+    history: ResMut<'w, HistoryBuffer>,
+    state_history: ResMut<'w, StateHistory>,
+    sim_history: ResMut<'w, SimHistory>,
+    q_camera: Query<
+        'w,
+        's,
+        (&'static mut Transform, &'static mut OrthographicProjection),
+        With<MainCamera>,
+    >,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+
+impl SessionState<'_, '_> {
+    fn capture(&self) -> Session {
+        let (transform, projection) = self.q_camera.single();
+        Session {
+            living: self.living.0.iter().copied().collect(),
+            ages: self
+                .ages
+                .0
+                .iter()
+                .map(|(cell, age)| (*cell, *age))
+                .collect(),
+            species: self
+                .species
+                .0
+                .iter()
+                .map(|(cell, kind)| (*cell, *kind))
+                .collect(),
+            rules: self
+                .rules
+                .0
+                .iter()
+                .map(|rules| LifeRules {
+                    survive: rules.survive.clone(),
+                    born: rules.born.clone(),
+                    max_age: rules.max_age,
+                })
+                .collect(),
+            camera: transform.translation.truncate().to_array(),
+            zoom: projection.scale,
+            hex_size: self.hex_size.0,
+            theme: self.theme.name.to_string(),
+            generation: self.generation.0,
+        }
+    }
+
+    fn restore(&mut self, session: Session) {
+        // Undo takes the board back to before the load; ticks from before it are gone.
+        self.history.push(&self.living.0);
+        self.state_history.hashes.clear();
+        self.sim_history.snapshots.clear();
+        self.living.0 = session.living.into_iter().collect();
+        self.ages.0 = session.ages.into_iter().collect();
+        self.species.0 = session.species.into_iter().collect();
+        // A session without rules would leave nothing to simulate with.
+        if !session.rules.is_empty() {
+            self.rules.0 = session.rules;
+            self.rules.0.truncate(MAX_SPECIES);
+        }
+        if session.hex_size > 0. {
+            self.hex_size.0 = session.hex_size;
+        }
+        match THEMES.iter().find(|theme| theme.name == session.theme) {
+            Some(theme) => *self.theme = *theme,
+            None => warn!("unknown theme {:?} in {}", session.theme, SESSION_FILE),
+        }
+        self.generation.0 = session.generation;
+        for (mut transform, mut projection) in self.q_camera.iter_mut() {
+            transform.translation = Vec2::from(session.camera).extend(transform.translation.z);
+            if session.zoom > 0. {
+                projection.scale = session.zoom;
+            }
+        }
+    }
+}
+
+fn save_session(kb: Res<Input<KeyCode>>, state: SessionState) {
+    if !kb.just_pressed(KeyCode::F6) {
+        return;
+    }
+    let session = state.capture();
+    let text = match ron::ser::to_string_pretty(&session, ron::ser::PrettyConfig::default()) {
+        Ok(text) => text,
+        Err(err) => {
+            error!("failed to serialize the session: {}", err);
+            return;
+        }
+    };
+    match fs::write(SESSION_FILE, text) {
+        Ok(()) => info!("saved the session to {}", SESSION_FILE),
+        Err(err) => error!("failed to write {}: {}", SESSION_FILE, err),
+    }
+}
+
+fn load_session(kb: Res<Input<KeyCode>>, mut state: SessionState) {
+    if !kb.just_pressed(KeyCode::F9) {
+        return;
+    }
+    match read_session() {
+        Ok(session) => {
+            state.restore(session);
+            info!("loaded the session from {}", SESSION_FILE);
+        }
+        Err(err) => warn!("{}; keeping the current session", err),
+    }
+}
+
+/// Restores the last saved session at startup, if there is one.
+fn autoload_session(mut state: SessionState) {
+    if fs::metadata(SESSION_FILE).is_err() {
+        return;
+    }
+    match read_session() {
+        Ok(session) => {
+            state.restore(session);
+            info!("resumed the session from {}", SESSION_FILE);
+        }
+        Err(err) => warn!("{}; starting a new session", err),
+    }
+}
+
+fn read_session() -> Result<Session, String> {
+    let text = fs::read_to_string(SESSION_FILE)
+        .map_err(|err| format!("failed to read {}: {}", SESSION_FILE, err))?;
+    ron::from_str(&text).map_err(|err| format!("failed to parse {}: {}", SESSION_FILE, err))
+}
@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+use bevy::{
+    audio::{play_queued_audio_system, AudioOutput, Decodable},
+    prelude::*,
+    reflect::TypeUuid,
+};
+use rodio::source::{Amplify, SineWave, Source, TakeDuration};
+
+use crate::GenerationAdvanced;
+
+// Length of the click played after a tick.
+const BLIP_SECONDS: f32 = 0.04;
+// Least time between two clicks, so fast ticks don't blur into a drone.
+const BLIP_COOLDOWN: f32 = 0.12;
+const BLIP_BASE_PITCH: f32 = 220.;
+const BLIP_VOLUME: f32 = 0.2;
+
+/// Clicks after every tick that had births, higher pitched the more cells were born.
+/// B toggles it; it starts muted.
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_non_send_resource::<AudioOutput<Blip>>()
+            .add_asset::<Blip>()
+            .init_resource::<Audio<Blip>>()
+            .init_resource::<SoundEnabled>()
+            .add_system(sound_control)
+            .add_system(play_births)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                play_queued_audio_system::<Blip>.exclusive_system(),
+            );
+    }
+}
+
+#[derive(Default)]
+struct SoundEnabled(bool);
+
+/// A short sine tone, synthesized when played so no sound files are needed.
+#[derive(Clone, TypeUuid)]
+#[uuid = "a0ee7873-55d3-4197-9e5d-50c87e88164a"]
+struct Blip {
+    pitch: f32,
+}
+
+impl Decodable for Blip {
+    type Decoder = Amplify<TakeDuration<SineWave>>;
+    type DecoderItem = f32;
+
+    fn decoder(&self) -> Self::Decoder {
+        let mut tone =
+            SineWave::new(self.pitch as u32).take_duration(Duration::from_secs_f32(BLIP_SECONDS));
+        // Fading out avoids a pop where the wave is cut off.
+        tone.set_filter_fadeout();
+        tone.amplify(BLIP_VOLUME)
+    }
+}
+
+fn sound_control(kb: Res<Input<KeyCode>>, mut enabled: ResMut<SoundEnabled>) {
+    if kb.just_pressed(KeyCode::B) {
+        enabled.0 = !enabled.0;
+        info!("sound {}", if enabled.0 { "on" } else { "off" });
+    }
+}
+
+fn play_births(
+    time: Res<Time>,
+    enabled: Res<SoundEnabled>,
+    audio: Res<Audio<Blip>>,
+    mut blips: ResMut<Assets<Blip>>,
+    mut events: EventReader<GenerationAdvanced>,
+    mut last_played: Local<f64>,
+) {
+    let births: usize = events.iter().map(|event| event.births.len()).sum();
+    if !enabled.0 || births == 0 {
+        return;
+    }
+    let now = time.seconds_since_startup();
+    if now - *last_played < BLIP_COOLDOWN as f64 {
+        return;
+    }
+    *last_played = now;
+    // Up an octave for every tenfold increase in births.
+    let pitch = BLIP_BASE_PITCH * 2f32.powf((births as f32).log10());
+    audio.play(blips.add(Blip { pitch }));
+}